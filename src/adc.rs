@@ -15,7 +15,7 @@
 //! - Additional power saving (peripheral clock)
 //! - Automatic comparison
 
-use crate::clock::{AdcClock, Enabled};
+use crate::clock::{AdcClock, ClockChangeListener, Clocks, Enabled};
 use crate::gpio::*;
 use crate::hal::adc::{Channel, OneShot};
 use crate::pac::ADC;
@@ -24,7 +24,6 @@ use core::marker::PhantomData;
 use core::sync::atomic::{compiler_fence, Ordering};
 use cortex_m::singleton;
 use embedded_dma::WriteBuffer;
-use fugit::RateExtU32;
 
 #[derive(PartialEq, Eq, Copy, Clone, Debug, defmt::Format)]
 pub enum Powersaving {
@@ -175,6 +174,7 @@ pub enum TransferTime {
 /// let mut adc = Adc::new(
 ///     peripherals.ADC,
 ///     clocks.peripheral_clocks.adc.into_enabled_clock(),
+///     clocks.clocks,
 /// );
 /// // Channels, gain and offset must be enabled and set before starting autocalibration
 /// adc.enable_channel(&mut pins.sense1); // pin sense1 = a17<ExFn, into_extra_function>
@@ -241,6 +241,7 @@ where
 /// let mut adc = Adc::new(
 ///     peripherals.ADC,
 ///     clocks.peripheral_clocks.adc.into_enabled_clock(),
+///     clocks.clocks,
 /// );
 /// // Channels, gain and offset must be enabled and set before starting autocalibration
 /// adc.enable_channel(&mut pins.sense1); // pin sense1 = a17<ExFn, into_extra_function>
@@ -253,7 +254,7 @@ where
 /// ```
 pub struct Adc {
     adc: ADC,
-    clock: PhantomData<AdcClock<Enabled>>,
+    clock: AdcClock<Enabled>,
 }
 
 pub struct SingleConversion;
@@ -267,10 +268,27 @@ pub trait ConversionMode {
     fn on_stop<PIN: Channel<ADC, ID = u8>>(adc: &mut Adc, pin: &mut PIN);
 }
 
+impl ClockChangeListener for Adc {
+    /// Recomputes the prescaler to keep the ADC clock at or below 20 MHz
+    fn clocks_changed(&mut self, clocks: Clocks) {
+        Self::set_prescaler(&self.adc, clocks);
+    }
+}
+
 impl Adc {
+    /// Sets PRESCAL for an ADC clock of at most 20 MHz: ADCClock = MCK / ((PRESCAL + 1) * 2)
+    fn set_prescaler(adc: &ADC, clocks: Clocks) {
+        let prescaler = clocks
+            .master_clock()
+            .raw()
+            .div_ceil(2 * 20_000_000)
+            .saturating_sub(1) as u8;
+        adc.mr.modify(|_, w| unsafe { w.prescal().bits(prescaler) });
+    }
+
     /// Default ADC initialization using 20 MHz ADC clock
     /// Uses typical configuration.
-    pub fn new(adc: ADC, clock: AdcClock<Enabled>) -> Self {
+    pub fn new(adc: ADC, clock: AdcClock<Enabled>, clocks: Clocks) -> Self {
         // Clear ADC write-protect
         adc.wpmr
             .modify(|_, w| w.wpkey().passwd().wpen().clear_bit());
@@ -305,9 +323,8 @@ impl Adc {
         }
 
         // Setup prescalar and startup time
-        let prescaler = (clock.frequency() / (2 * 20_u32.MHz::<1, 1>()) - 1) as u8;
-        adc.mr
-            .modify(|_, w| unsafe { w.prescal().bits(prescaler).startup().sut512() });
+        Self::set_prescaler(&adc, clocks);
+        adc.mr.modify(|_, w| w.startup().sut512());
 
         /* Set ADC timing.
          * Formula:
@@ -379,10 +396,15 @@ impl Adc {
         // Allow different gain/offset values for each channel
         adc.mr.modify(|_, w| w.anach().allowed());
 
-        Self {
-            adc,
-            clock: PhantomData,
+        Self { adc, clock }
+    }
+
+    /// Resets the ADC and releases the peripheral and clock
+    pub fn free(self) -> (ADC, AdcClock<Enabled>) {
+        unsafe {
+            self.adc.cr.write_with_zero(|w| w.swrst().set_bit());
         }
+        (self.adc, self.clock)
     }
 
     /// Startup time
@@ -478,6 +500,7 @@ impl Adc {
     /// let mut adc = Adc::new(
     ///     peripherals.ADC,
     ///     clocks.peripheral_clocks.adc.into_enabled_clock(),
+    ///     clocks.clocks,
     /// );
     /// let temp_sensor = adc.temp_sensor();
     /// adc.enable_channel(temp_sensor);
@@ -1069,6 +1092,7 @@ impl Adc {
     /// let mut adc = Adc::new(
     ///     cx.device.ADC,
     ///     clocks.peripheral_clocks.adc.into_enabled_clock(),
+    ///     clocks.clocks,
     /// );
     ///
     /// // Enable 3 channels
@@ -1102,6 +1126,7 @@ impl Adc {
     ///     let mut adc = Adc::new(
     ///         cx.device.ADC,
     ///         clocks.peripheral_clocks.adc.into_enabled_clock(),
+    ///         clocks.clocks,
     ///     );
     ///
     ///     // Enable 3 channels
@@ -1150,6 +1175,7 @@ impl Adc {
     /// let mut adc = Adc::new(
     ///     cx.device.ADC,
     ///     clocks.peripheral_clocks.adc.into_enabled_clock(),
+    ///     clocks.clocks,
     /// );
     ///
     /// // Enable 3 channels
@@ -1189,6 +1215,7 @@ impl Adc {
     ///     let mut adc = Adc::new(
     ///         cx.device.ADC,
     ///         clocks.peripheral_clocks.adc.into_enabled_clock(),
+    ///         clocks.clocks,
     ///     );
     ///
     ///     // Enable 3 channels
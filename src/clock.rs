@@ -11,15 +11,101 @@ use crate::pac::EFC0;
 #[cfg(feature = "atsam4sd")]
 use crate::pac::EFC1;
 
+use crate::gpio::{Pa17, Pa18, Pa21, Pa31, Pa6, Pb13, Pb3, PfB};
 use core::marker::PhantomData;
 use fugit::{HertzU32 as Hertz, RateExtU32};
 
-static mut MASTER_CLOCK_FREQUENCY: Hertz = Hertz::from_raw(0);
+// Only valid when the main clock is sourced from the crystal oscillator
+static mut MAIN_CRYSTAL_FREQUENCY: Hertz = Hertz::from_raw(0);
 
-#[cfg(all(feature = "atsam4s", feature = "usb"))]
+#[cfg(feature = "atsam4s")]
 static mut PLLB_MULTIPLIER: u16 = 0;
 
-// NOTE: More frequencies and crystals can be added
+// Maximum master clock frequency supported by the device
+#[cfg(feature = "atsam4n")]
+const MASTER_CLOCK_MAX: u32 = 100_000_000;
+#[cfg(not(feature = "atsam4n"))]
+const MASTER_CLOCK_MAX: u32 = 120_000_000;
+
+// Slow clock frequency (32.768 kHz crystal or ~32 kHz RC oscillator)
+pub(crate) const SLOW_CLOCK_FREQUENCY: u32 = 32_768;
+
+// Main crystal oscillator frequency range (ATSAM4E 45.5.1, ATSAM4S 44.5.3)
+const CRYSTAL_MIN: u32 = 3_000_000;
+const CRYSTAL_MAX: u32 = 20_000_000;
+
+// PLL input (after the divider) and output frequency ranges (ATSAM4E 45.5.5, ATSAM4S 44.6)
+const PLL_INPUT_MIN: u32 = 3_000_000;
+const PLL_INPUT_MAX: u32 = 32_000_000;
+const PLL_OUTPUT_MIN: u32 = 80_000_000;
+const PLL_OUTPUT_MAX: u32 = 240_000_000;
+
+// MUL can be set from 1 up to 62, the PLL multiplies by MUL + 1
+const PLL_MULTIPLIER_MAX: u16 = 63;
+
+// USB requires exactly 48 MHz, the divider is USBDIV + 1
+#[cfg(not(feature = "atsam4n"))]
+const USB_CLOCK: u32 = 48_000_000;
+#[cfg(not(feature = "atsam4n"))]
+const USB_DIVIDER_MAX: u32 = 16;
+
+// Maximum master clock frequency for each flash wait state count (FWS = index)
+// "Embedded Flash Wait State" tables from the electrical characteristics of each datasheet
+#[cfg(feature = "atsam4e")]
+const FLASH_WAIT_STATES_1V08: &[u32] = &[
+    16_000_000,
+    33_000_000,
+    50_000_000,
+    67_000_000,
+    84_000_000,
+    100_000_000,
+];
+#[cfg(feature = "atsam4e")]
+const FLASH_WAIT_STATES_1V20: &[u32] = &[
+    20_000_000,
+    57_000_000,
+    68_000_000,
+    85_000_000,
+    104_000_000,
+    123_000_000,
+];
+#[cfg(feature = "atsam4s")]
+const FLASH_WAIT_STATES_1V08: &[u32] = &[
+    16_000_000,
+    33_000_000,
+    50_000_000,
+    67_000_000,
+    84_000_000,
+    100_000_000,
+];
+#[cfg(feature = "atsam4s")]
+const FLASH_WAIT_STATES_1V20: &[u32] = &[
+    20_000_000,
+    40_000_000,
+    60_000_000,
+    80_000_000,
+    100_000_000,
+    123_000_000,
+];
+#[cfg(feature = "atsam4n")]
+const FLASH_WAIT_STATES_1V08: &[u32] =
+    &[21_000_000, 42_000_000, 63_000_000, 84_000_000, 100_000_000];
+#[cfg(feature = "atsam4n")]
+const FLASH_WAIT_STATES_1V20: &[u32] =
+    &[24_000_000, 48_000_000, 72_000_000, 96_000_000, 100_000_000];
+
+// Master clock prescaler values (divider, PMC_MCKR.PRES)
+const PRESCALERS: [(u32, u8); 8] = [
+    (1, 0),
+    (2, 1),
+    (3, 7),
+    (4, 2),
+    (8, 3),
+    (16, 4),
+    (32, 5),
+    (64, 6),
+];
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MainClock {
     #[cfg(not(feature = "atsam4n"))]
@@ -27,9 +113,371 @@ pub enum MainClock {
     RcOscillator8Mhz,  // USB Unsupported
     RcOscillator12Mhz, // USB Unsupported
     Crystal12Mhz,      // USB Supported
-                       // Crystal11289Khz, // USB Supported - Not implemented
-                       // Crystal16MHz     // USB Supported - Not implemented
-                       // Crystal18432Khz, // USB Supported - Not implemented
+    Crystal(Hertz),    // USB Supported (3 to 20 MHz, PLL must be able to generate 48 MHz)
+}
+
+impl MainClock {
+    /// Frequency of the selected main clock source
+    pub fn frequency(&self) -> Hertz {
+        match self {
+            #[cfg(not(feature = "atsam4n"))]
+            MainClock::RcOscillator4Mhz => 4_u32.MHz(),
+            MainClock::RcOscillator8Mhz => 8_u32.MHz(),
+            MainClock::RcOscillator12Mhz => 12_u32.MHz(),
+            MainClock::Crystal12Mhz => 12_u32.MHz(),
+            MainClock::Crystal(frequency) => *frequency,
+        }
+    }
+
+    /// Returns `true` if the main clock is sourced from the crystal oscillator
+    pub fn is_crystal(&self) -> bool {
+        matches!(self, MainClock::Crystal12Mhz | MainClock::Crystal(_))
+    }
+}
+
+/// Master clock source (PMC_MCKR.CSS)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum MasterClockSource {
+    /// Slow clock (32.768 kHz), lowest power
+    SlowClock,
+    /// Main clock (RC oscillator or crystal) without the PLL
+    MainClock,
+    /// PLLA, required for master clocks above the main clock frequency
+    PllaClock,
+}
+
+impl MasterClockSource {
+    /// PMC_MCKR.CSS value
+    fn css(&self) -> u8 {
+        match self {
+            MasterClockSource::SlowClock => 0,
+            MasterClockSource::MainClock => 1,
+            MasterClockSource::PllaClock => 2,
+        }
+    }
+}
+
+/// Core supply voltage (VDDCORE), selects the flash wait state table
+///
+/// Select `Vddcore1V08` unless VDDCORE is guaranteed to stay at or above 1.20 V.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum CoreVoltage {
+    /// VDDCORE 1.08 V
+    Vddcore1V08,
+    /// VDDCORE 1.20 V
+    Vddcore1V20,
+}
+
+impl CoreVoltage {
+    /// Flash wait states (FWS) needed for the given master clock frequency
+    pub fn flash_wait_states(&self, master_clock: Hertz) -> Result<u8, ClockError> {
+        let table = match self {
+            CoreVoltage::Vddcore1V08 => FLASH_WAIT_STATES_1V08,
+            CoreVoltage::Vddcore1V20 => FLASH_WAIT_STATES_1V20,
+        };
+        table
+            .iter()
+            .position(|max| master_clock.raw() <= *max)
+            .map(|fws| fws as u8)
+            .ok_or(ClockError::FlashTooSlow(master_clock))
+    }
+}
+
+/// Clock configuration errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum ClockError {
+    /// Crystal frequency is outside of the main oscillator range (3 to 20 MHz)
+    InvalidCrystalFrequency(Hertz),
+    /// Requested master clock is 0 or above the maximum supported by the device
+    InvalidMasterClockFrequency(Hertz),
+    /// No PLL multiplier, divider and prescaler combination can generate the master clock
+    NoPllSolution,
+    /// Master clock is too fast for the flash at the configured core voltage
+    FlashTooSlow(Hertz),
+    /// No PLL multiplier and divider combination can generate an exact 48 MHz USB clock
+    NoUsbSolution,
+    /// USB requires the main clock to be sourced from a crystal
+    UsbRequiresCrystal,
+    /// USB is not available on this device
+    UsbUnsupported,
+}
+
+/// PLL settings
+/// f_out = f_in * multiplier / divider
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct PllSettings {
+    /// MUL + 1 (2 to 63)
+    pub multiplier: u16,
+    /// DIV (1 to 255)
+    pub divider: u8,
+}
+
+impl PllSettings {
+    /// PLL output frequency for the given input frequency
+    pub fn output(&self, input: Hertz) -> Hertz {
+        Hertz::from_raw((input.raw() as u64 * self.multiplier as u64 / self.divider as u64) as u32)
+    }
+}
+
+/// Register settings computed by [`ClockConfig::solve`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct ClockSolution {
+    /// Master clock source
+    pub source: MasterClockSource,
+    /// PLLA settings, None if PLLA is not needed for the master clock or USB
+    pub plla: Option<PllSettings>,
+    /// PMC_MCKR.PRES value
+    pub prescaler: u8,
+    /// PLLB settings (atsam4s only, used to generate the USB clock)
+    pub pllb: Option<PllSettings>,
+    /// USB clock divider (USBDIV + 1), None if USB is not configured
+    pub usb_divider: Option<u8>,
+    /// Resulting master clock frequency
+    pub master_clock: Hertz,
+    /// EEFC_FMR.FWS value for the master clock
+    pub flash_wait_states: u8,
+}
+
+/// Main clock configuration
+///
+/// The PLL settings are computed from the main clock frequency. If the requested master clock
+/// cannot be generated exactly, the closest frequency below it is used.
+/// The USB clock must always be exactly 48 MHz.
+///
+/// For low power operation the master clock can be taken directly from the main clock or the
+/// slow clock (see [`ClockConfig::with_source`]), in which case only the prescaler is used.
+///
+/// ```
+/// let config = ClockConfig::new(MainClock::Crystal(16_u32.MHz()), 120_u32.MHz(), true);
+/// let clocks = ClockController::with_config(
+///     peripherals.PMC,
+///     &peripherals.SUPC,
+///     &peripherals.EFC,
+///     config,
+///     SlowClock::RcOscillator32Khz,
+/// )
+/// .unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClockConfig {
+    /// Main clock source
+    pub main_clock: MainClock,
+    /// Requested master clock frequency
+    pub master_clock: Hertz,
+    /// Generate the 48 MHz USB clock
+    pub usb: bool,
+    /// Master clock source
+    pub source: MasterClockSource,
+    /// Core voltage (VDDCORE), used to select the flash wait states
+    pub core_voltage: CoreVoltage,
+}
+
+impl ClockConfig {
+    pub fn new(main_clock: MainClock, master_clock: Hertz, usb: bool) -> Self {
+        Self {
+            main_clock,
+            master_clock,
+            usb,
+            source: MasterClockSource::PllaClock,
+            core_voltage: CoreVoltage::Vddcore1V20,
+        }
+    }
+
+    /// Selects the flash wait state table (VDDCORE 1.20 V by default)
+    pub fn with_core_voltage(mut self, core_voltage: CoreVoltage) -> Self {
+        self.core_voltage = core_voltage;
+        self
+    }
+
+    /// Selects the master clock source (PLLA by default)
+    ///
+    /// ```
+    /// // 4 MHz RC oscillator divided by 4, PLLA is disabled
+    /// let config = ClockConfig::new(MainClock::RcOscillator4Mhz, 1_u32.MHz(), false)
+    ///     .with_source(MasterClockSource::MainClock);
+    /// ```
+    pub fn with_source(mut self, source: MasterClockSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Computes PLL, prescaler and USB divider settings for this configuration
+    pub fn solve(&self) -> Result<ClockSolution, ClockError> {
+        let input = self.main_clock.frequency().raw();
+        let target = self.master_clock.raw();
+
+        if self.main_clock.is_crystal() && !(CRYSTAL_MIN..=CRYSTAL_MAX).contains(&input) {
+            return Err(ClockError::InvalidCrystalFrequency(
+                self.main_clock.frequency(),
+            ));
+        }
+        if target == 0 || target > MASTER_CLOCK_MAX {
+            return Err(ClockError::InvalidMasterClockFrequency(self.master_clock));
+        }
+        if self.usb && cfg!(feature = "atsam4n") {
+            return Err(ClockError::UsbUnsupported);
+        }
+        if self.usb && !self.main_clock.is_crystal() {
+            return Err(ClockError::UsbRequiresCrystal);
+        }
+
+        // atsam4e only has PLLA, so USB must be derived from the same PLL as the master clock
+        let usb_from_plla = self.usb && cfg!(feature = "atsam4e");
+
+        if self.source != MasterClockSource::PllaClock {
+            return self.solve_without_pll(input, target, usb_from_plla);
+        }
+
+        // Search for the master clock closest to (but not above) the target
+        // Ties are broken using the lowest PLL output (lower power) and then the lowest divider
+        let mut best: Option<(PllSettings, u8, u32, u32)> = None;
+        for pll in pll_settings(input) {
+            let output = pll.output(self.main_clock.frequency()).raw();
+            if usb_from_plla && usb_divider(input, pll).is_none() {
+                continue;
+            }
+
+            for (div, pres) in PRESCALERS {
+                let mck = output / div;
+                if mck > target {
+                    continue;
+                }
+                let better = match best {
+                    None => true,
+                    Some((_, _, best_mck, best_output)) => {
+                        mck > best_mck || (mck == best_mck && output < best_output)
+                    }
+                };
+                if better {
+                    best = Some((pll, pres, mck, output));
+                }
+            }
+        }
+        let (plla, prescaler, master_clock, _) = match best {
+            Some(best) => best,
+            None if usb_from_plla => return Err(ClockError::NoUsbSolution),
+            None => return Err(ClockError::NoPllSolution),
+        };
+
+        let (pllb, usb) = if !self.usb {
+            (None, None)
+        } else if usb_from_plla {
+            (None, usb_divider(input, plla))
+        } else {
+            let pll = usb_pll_settings(input)?;
+            (Some(pll), usb_divider(input, pll))
+        };
+
+        Ok(ClockSolution {
+            source: self.source,
+            plla: Some(plla),
+            prescaler,
+            pllb,
+            usb_divider: usb,
+            master_clock: master_clock.Hz(),
+            flash_wait_states: self.core_voltage.flash_wait_states(master_clock.Hz())?,
+        })
+    }
+
+    /// Master clock taken directly from the slow clock or main clock using only the prescaler
+    /// A PLL is still started if USB is requested
+    fn solve_without_pll(
+        &self,
+        input: u32,
+        target: u32,
+        usb_from_plla: bool,
+    ) -> Result<ClockSolution, ClockError> {
+        let source_frequency = match self.source {
+            MasterClockSource::SlowClock => SLOW_CLOCK_FREQUENCY,
+            _ => input,
+        };
+
+        // Highest master clock that does not exceed the target
+        let (prescaler, master_clock) = PRESCALERS
+            .iter()
+            .map(|(div, pres)| (*pres, source_frequency / div))
+            .filter(|(_, mck)| *mck <= target)
+            .max_by_key(|(_, mck)| *mck)
+            .ok_or(ClockError::InvalidMasterClockFrequency(self.master_clock))?;
+
+        let (plla, pllb, usb) = if !self.usb {
+            (None, None, None)
+        } else {
+            let pll = usb_pll_settings(input)?;
+            if usb_from_plla {
+                (Some(pll), None, usb_divider(input, pll))
+            } else {
+                (None, Some(pll), usb_divider(input, pll))
+            }
+        };
+
+        Ok(ClockSolution {
+            source: self.source,
+            plla,
+            prescaler,
+            pllb,
+            usb_divider: usb,
+            master_clock: master_clock.Hz(),
+            flash_wait_states: self.core_voltage.flash_wait_states(master_clock.Hz())?,
+        })
+    }
+}
+
+impl From<MainClock> for ClockConfig {
+    /// Runs the master clock as fast as possible
+    /// USB is configured when the usb feature is enabled and a crystal is used
+    fn from(main_clock: MainClock) -> Self {
+        Self::new(
+            main_clock,
+            MASTER_CLOCK_MAX.Hz(),
+            cfg!(feature = "usb") && !cfg!(feature = "atsam4n") && main_clock.is_crystal(),
+        )
+    }
+}
+
+/// Iterates over all valid PLL settings for the given input frequency
+/// Ordered by divider, then multiplier
+fn pll_settings(input: u32) -> impl Iterator<Item = PllSettings> {
+    (1..=u8::MAX)
+        .take_while(move |divider| input / *divider as u32 >= PLL_INPUT_MIN)
+        .filter(move |divider| input / *divider as u32 <= PLL_INPUT_MAX)
+        .flat_map(move |divider| {
+            (2..=PLL_MULTIPLIER_MAX).map(move |multiplier| PllSettings {
+                multiplier,
+                divider,
+            })
+        })
+        .filter(move |pll| {
+            let output = pll.output(input.Hz()).raw();
+            (PLL_OUTPUT_MIN..=PLL_OUTPUT_MAX).contains(&output)
+        })
+}
+
+/// Lowest PLL frequency that can generate the 48 MHz USB clock
+fn usb_pll_settings(input: u32) -> Result<PllSettings, ClockError> {
+    pll_settings(input)
+        .filter(|pll| usb_divider(input, *pll).is_some())
+        .min_by_key(|pll| pll.output(input.Hz()).raw())
+        .ok_or(ClockError::NoUsbSolution)
+}
+
+/// USB divider needed to generate exactly 48 MHz from the PLL output
+#[cfg(not(feature = "atsam4n"))]
+fn usb_divider(input: u32, pll: PllSettings) -> Option<u8> {
+    let numerator = input as u64 * pll.multiplier as u64;
+    if !numerator.is_multiple_of(pll.divider as u64) {
+        return None;
+    }
+    let output = (numerator / pll.divider as u64) as u32;
+    if !output.is_multiple_of(USB_CLOCK) || !(1..=USB_DIVIDER_MAX).contains(&(output / USB_CLOCK)) {
+        return None;
+    }
+    Some((output / USB_CLOCK) as u8)
+}
+
+#[cfg(feature = "atsam4n")]
+fn usb_divider(_input: u32, _pll: PllSettings) -> Option<u8> {
+    None
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -39,8 +487,107 @@ pub enum SlowClock {
     OscillatorBypass32Khz,
 }
 
-pub fn get_master_clock_frequency() -> Hertz {
-    unsafe { MASTER_CLOCK_FREQUENCY }
+/// Frozen clock frequencies
+///
+/// Obtained from [`ClockController`] once the clocks have been configured and passed to driver
+/// constructors, so a driver can never be created before the clocks are running.
+///
+/// ```
+/// let clocks = ClockController::new(
+///     peripherals.PMC,
+///     &peripherals.SUPC,
+///     &peripherals.EFC,
+///     MainClock::Crystal12Mhz,
+///     SlowClock::RcOscillator32Khz,
+/// );
+/// let mut delay = Delay::new(core.SYST, clocks.clocks);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Clocks {
+    master_clock: Hertz,
+    slow_clock: Hertz,
+    main_clock: Hertz,
+    plla: Option<Hertz>,
+    pllb: Option<Hertz>,
+    usb: Option<Hertz>,
+}
+
+impl Clocks {
+    fn new(
+        main_clock: Hertz,
+        slow_clock: Hertz,
+        master_clock: Hertz,
+        solution: &ClockSolution,
+    ) -> Self {
+        // USB is clocked from PLLB on atsam4s (USBS set), PLLA otherwise
+        #[cfg(feature = "atsam4s")]
+        let usb_pll = solution.pllb.or(solution.plla);
+        #[cfg(not(feature = "atsam4s"))]
+        let usb_pll = solution.plla;
+
+        Clocks {
+            master_clock,
+            slow_clock,
+            main_clock,
+            plla: solution.plla.map(|pll| pll.output(main_clock)),
+            pllb: solution.pllb.map(|pll| pll.output(main_clock)),
+            usb: solution
+                .usb_divider
+                .zip(usb_pll)
+                .map(|(usbdiv, pll)| pll.output(main_clock) / usbdiv as u32),
+        }
+    }
+
+    /// Master clock (MCK), also used by the core and peripherals
+    pub fn master_clock(&self) -> Hertz {
+        self.master_clock
+    }
+
+    /// Replaces the master clock frequency with a measured frequency
+    /// (see [`ClockController::trimmed_clocks`])
+    pub(crate) fn with_master_clock(mut self, master_clock: Hertz) -> Self {
+        self.master_clock = master_clock;
+        self
+    }
+
+    /// Slow clock (SLCK)
+    pub fn slow_clock(&self) -> Hertz {
+        self.slow_clock
+    }
+
+    /// Main clock (MAINCK), RC oscillator or crystal
+    pub fn main_clock(&self) -> Hertz {
+        self.main_clock
+    }
+
+    /// PLLA output, None if PLLA is disabled
+    pub fn plla(&self) -> Option<Hertz> {
+        self.plla
+    }
+
+    /// PLLB output, None if PLLB is disabled (atsam4s only)
+    pub fn pllb(&self) -> Option<Hertz> {
+        self.pllb
+    }
+
+    /// USB clock, None if USB is not configured
+    pub fn usb(&self) -> Option<Hertz> {
+        self.usb
+    }
+}
+
+/// Implemented by drivers that derive dividers (baud rate, timer ticks, etc.) from the master clock
+///
+/// Must be called after [`ClockController::reconfigure`] so the driver can recompute them.
+///
+/// ```
+/// let clocks = controller.reconfigure(&peripherals.EFC, config).unwrap();
+/// uart.clocks_changed(clocks);
+/// delay.clocks_changed(clocks);
+/// ```
+pub trait ClockChangeListener {
+    /// Recomputes dividers for the new clock frequencies
+    fn clocks_changed(&mut self, clocks: Clocks);
 }
 
 fn setup_slow_clock(supc: &SUPC, slow_clock: SlowClock) -> Hertz {
@@ -66,177 +613,151 @@ fn setup_slow_clock(supc: &SUPC, slow_clock: SlowClock) -> Hertz {
         }
     }
     // 32.768 kHz
-    32768.Hz()
+    SLOW_CLOCK_FREQUENCY.Hz()
 }
 
-fn setup_main_clock(pmc: &PMC, main_clock: MainClock) -> Hertz {
-    let prescaler = match main_clock {
+fn setup_main_clock(pmc: &PMC, main_clock: MainClock, solution: &ClockSolution) -> Hertz {
+    match main_clock {
         #[cfg(not(feature = "atsam4n"))]
-        MainClock::RcOscillator4Mhz => {
-            switch_main_clock_to_fast_rc_4mhz(pmc);
-
-            // Set up the PLL for 120Mhz operation (4Mhz RC * (30 / 1) = 120Mhz)
-            let multiplier: u16 = 30;
-            let divider: u8 = 1;
-            enable_plla_clock(pmc, multiplier, divider);
-
-            // 0 = no prescaling
-            0
+        MainClock::RcOscillator4Mhz => switch_main_clock_to_fast_rc_4mhz(pmc),
+        MainClock::RcOscillator8Mhz => switch_main_clock_to_fast_rc_8mhz(pmc),
+        MainClock::RcOscillator12Mhz => switch_main_clock_to_fast_rc_12mhz(pmc),
+        MainClock::Crystal12Mhz | MainClock::Crystal(_) => {
+            unsafe {
+                MAIN_CRYSTAL_FREQUENCY = main_clock.frequency();
+            }
+            switch_main_clock_to_external_crystal(pmc);
         }
-        #[cfg(not(feature = "atsam4n"))]
-        MainClock::RcOscillator8Mhz => {
-            switch_main_clock_to_fast_rc_8mhz(pmc);
+    }
 
-            // Set up the PLL for 120Mhz operation (8Mhz RC * (15 / 1) = 120Mhz)
-            let multiplier: u16 = 15;
-            let divider: u8 = 1;
-            enable_plla_clock(pmc, multiplier, divider);
+    if let Some(plla) = solution.plla {
+        enable_plla_clock(pmc, plla.multiplier, plla.divider);
+    }
 
-            // 0 = no prescaling
-            0
-        }
-        #[cfg(feature = "atsam4n")]
-        MainClock::RcOscillator8Mhz => {
-            switch_main_clock_to_fast_rc_8mhz(pmc);
+    // PLLB is only used to generate the USB clock
+    #[cfg(feature = "atsam4s")]
+    match solution.pllb {
+        Some(pllb) => enable_pllb_clock(pmc, pllb.multiplier, pllb.divider),
+        None => disable_pllb_clock(pmc),
+    }
 
-            // Set up the PLL for 100Mhz operation (8Mhz RC * (25 / 2) = 100Mhz)
-            let multiplier: u16 = 25;
-            let divider: u8 = 2;
-            enable_plla_clock(pmc, multiplier, divider);
+    wait_for_main_clock_ready(pmc);
 
-            // 0 = no prescaling
-            0
-        }
-        #[cfg(not(feature = "atsam4n"))]
-        MainClock::RcOscillator12Mhz => {
-            switch_main_clock_to_fast_rc_12mhz(pmc);
+    if solution.plla.is_some() {
+        wait_for_plla_lock(pmc);
+    }
 
-            // Set up the PLL for 120Mhz operation (12Mhz RC * (10 / 1) = 120Mhz)
-            let multiplier: u16 = 10;
-            let divider: u8 = 1;
-            enable_plla_clock(pmc, multiplier, divider);
+    match solution.source {
+        MasterClockSource::PllaClock => switch_master_clock_to_plla(pmc, solution.prescaler),
+        source => switch_master_clock_without_pll(pmc, source, solution.prescaler),
+    }
 
-            // 0 = no prescaling
-            0
-        }
-        #[cfg(feature = "atsam4n")]
-        MainClock::RcOscillator12Mhz => {
-            switch_main_clock_to_fast_rc_12mhz(pmc);
+    // Stop PLLA when it is not used to save power
+    if solution.plla.is_none() {
+        disable_plla_clock(pmc);
+    }
 
-            // Set up the PLL for 100Mhz operation (12Mhz RC * (25 / 3) = 100Mhz)
-            let multiplier: u16 = 25;
-            let divider: u8 = 3;
-            enable_plla_clock(pmc, multiplier, divider);
+    calculate_master_clock_frequency(pmc)
+}
 
-            // 0 = no prescaling
-            0
-        }
+#[cfg(not(feature = "atsam4n"))]
+fn setup_usb_clock(pmc: &PMC, solution: &ClockSolution) {
+    // Setup USB clock
+    // NOTE: the datasheet indicates divider is USBDIV + 1
+    if let Some(usbdiv) = solution.usb_divider {
+        // PLLA
+        // e.g. 240 MHz / 5 = 48 MHz
+        // sam4e only has 1 pll so the USB clock shares PLLA with the master clock
         #[cfg(feature = "atsam4e")]
-        MainClock::Crystal12Mhz => {
-            switch_main_clock_to_external_12mhz(pmc);
-
-            #[cfg(feature = "usb")]
-            {
-                // Set up the PLL for 240MHz operation (12 MHz * (20 / 1) = 240 MHz)
-                // 240 MHz can be used to generate both master 120 MHz clock and USB 48 MHz clock
-                let multiplier: u16 = 20;
-                let divider: u8 = 1;
-                enable_plla_clock(pmc, multiplier, divider);
-
-                // 1 = /2 prescaling
-                1
-            }
+        pmc.pmc_usb
+            .modify(|_, w| unsafe { w.usbdiv().bits(usbdiv - 1) });
 
-            #[cfg(not(feature = "usb"))]
-            {
-                // Set up the PLL for 120MHz operation (12 MHz * (10 / 1) = 120 MHz)
-                // Uses less power than running the PLL at 240 MHz
-                let multiplier: u16 = 10;
-                let divider: u8 = 1;
-                enable_plla_clock(pmc, multiplier, divider);
+        // Use PLLB for sam4s
+        // e.g. 96 MHz / 2 = 48 MHz
+        // Using plla and pllb, lower current usage can be achieved on sam4s
+        // Per the datasheet ~1 mA
+        #[cfg(feature = "atsam4s")]
+        {
+            wait_for_pllb_lock(pmc);
 
-                // 0 = no prescaling
-                0
-            }
+            pmc.pmc_usb
+                .modify(|_, w| unsafe { w.usbs().set_bit().usbdiv().bits(usbdiv - 1) });
         }
-        #[cfg(feature = "atsam4n")]
-        MainClock::Crystal12Mhz => {
-            switch_main_clock_to_external_12mhz(pmc);
-
-            // Set up the PLL for 100MHz operation (12 MHz * (25 / 3) = 100 MHz)
-            let multiplier: u16 = 25;
-            let divider: u8 = 3;
-            enable_plla_clock(pmc, multiplier, divider);
+    }
+}
 
-            // 0 = no prescaling
-            0
+#[cfg(feature = "atsam4n")]
+fn setup_usb_clock(_pmc: &PMC, _solution: &ClockSolution) {}
+
+fn calculate_main_clock_frequency(pmc: &pmc::RegisterBlock) -> Hertz {
+    match pmc.ckgr_mor.read().moscsel().bit_is_set() {
+        true => unsafe { MAIN_CRYSTAL_FREQUENCY },
+        false => {
+            if pmc.ckgr_mor.read().moscrcf().is_12_mhz() {
+                12_u32.MHz()
+            } else if pmc.ckgr_mor.read().moscrcf().is_8_mhz() {
+                8_u32.MHz()
+            } else if pmc.ckgr_mor.read().moscrcf().is_4_mhz() {
+                4_u32.MHz()
+            } else {
+                panic!("Unexpected value detected read from pmc.ckgr_mor.moscrcf")
+            }
         }
+    }
+}
 
-        #[cfg(feature = "atsam4s")]
-        MainClock::Crystal12Mhz => {
-            switch_main_clock_to_external_12mhz(pmc);
-
-            // Setup PLLA for 120 MHz operation (12 MHz * (10 / 1) = 120 MHz)
-            let multiplier: u16 = 10;
-            let divider: u8 = 1;
-            enable_plla_clock(pmc, multiplier, divider);
-
-            #[cfg(feature = "usb")]
-            {
-                // Setup PLLB for 96 MHz operation (12 MHz * (8 / 1) = 96 MHz)
-                // 96 MHz will be /2 to get 48 MHz
-                let multiplier: u16 = 8;
-                let divider: u8 = 1;
-                enable_pllb_clock(pmc, multiplier, divider);
-            }
+fn measure_main_clock_frequency(pmc: &PMC, slow_clock: Hertz) -> Hertz {
+    // Restart the measurement, MAINFRDY is cleared on the next slow clock edge and stays cleared
+    // until the count completes. Wait for it to clear so the previous result is not read.
+    pmc.ckgr_mcfr.modify(|_, w| w.rcmeas().set_bit());
+    while pmc.ckgr_mcfr.read().mainfrdy().bit_is_set() {}
+    while pmc.ckgr_mcfr.read().mainfrdy().bit_is_clear() {}
 
-            // 0 = no prescaling
-            0
-        }
-    };
-    wait_for_main_clock_ready(pmc);
+    // MAINF is the number of main clock cycles during 16 slow clock periods
+    let mainf = pmc.ckgr_mcfr.read().mainf().bits() as u32;
+    (mainf * slow_clock.raw() / 16).Hz()
+}
 
-    wait_for_plla_lock(pmc);
+fn calculate_plla_frequency(pmc: &pmc::RegisterBlock) -> Hertz {
+    let pllar = pmc.ckgr_pllar.read();
+    if pllar.mula().bits() == 0 || pllar.diva().bits() == 0 {
+        // PLLA is disabled
+        return 0.Hz();
+    }
 
-    switch_master_clock_to_plla(pmc, prescaler);
+    let mut plla_freq = calculate_main_clock_frequency(pmc);
+    plla_freq *= (pllar.mula().bits() + 1) as u32;
+    plla_freq /= (pllar.diva().bits()) as u32;
+    plla_freq
+}
 
-    calculate_master_clock_frequency(pmc)
+#[cfg(feature = "atsam4s")]
+fn calculate_pllb_frequency(pmc: &pmc::RegisterBlock) -> Hertz {
+    let pllbr = pmc.ckgr_pllbr.read();
+    if pllbr.mulb().bits() == 0 || pllbr.divb().bits() == 0 {
+        // PLLB is disabled
+        return 0.Hz();
+    }
+
+    let mut pllb_freq = calculate_main_clock_frequency(pmc);
+    pllb_freq *= (pllbr.mulb().bits() + 1) as u32;
+    pllb_freq /= (pllbr.divb().bits()) as u32;
+    pllb_freq
 }
 
-fn calculate_master_clock_frequency(pmc: &PMC) -> Hertz {
+fn calculate_master_clock_frequency(pmc: &pmc::RegisterBlock) -> Hertz {
     let mut mclk_freq = match pmc.pmc_mckr.read().css().bits() {
         0 => {
             // Slow clock
-            panic!("Unsupported clock source: Slow clock.")
+            SLOW_CLOCK_FREQUENCY.Hz()
         }
         1 => {
             // Main clock
-            panic!("Unsupported clock source: Main clock.")
+            calculate_main_clock_frequency(pmc)
         }
         2 => {
-            // PLL
-            let mut mclk_freq = match pmc.ckgr_mor.read().moscsel().bit_is_set() {
-                true => 12_u32.MHz(),
-                false => {
-                    if pmc.ckgr_mor.read().moscrcf().is_12_mhz() {
-                        12_u32.MHz()
-                    } else if pmc.ckgr_mor.read().moscrcf().is_8_mhz() {
-                        8_u32.MHz()
-                    } else if pmc.ckgr_mor.read().moscrcf().is_4_mhz() {
-                        4_u32.MHz()
-                    } else {
-                        panic!("Unexpected value detected read from pmc.ckgr_mor.moscrcf")
-                    }
-                }
-            };
-
-            let plla_clock_source: u8 = 2; // 2 = PLLA
-            if pmc.pmc_mckr.read().css().bits() == plla_clock_source {
-                mclk_freq *= (pmc.ckgr_pllar.read().mula().bits() + 1) as u32;
-                mclk_freq /= (pmc.ckgr_pllar.read().diva().bits()) as u32;
-            }
-
-            mclk_freq
+            // PLLA
+            calculate_plla_frequency(pmc)
         }
         _ => panic!("Invalid value found in PMC_MCKR.CSS"),
     };
@@ -250,21 +771,6 @@ fn calculate_master_clock_frequency(pmc: &PMC) -> Hertz {
     mclk_freq
 }
 
-fn get_flash_wait_states_for_clock_frequency(clock_frequency: Hertz) -> u8 {
-    match clock_frequency {
-        c if c < 20_u32.MHz::<1, 1>() => 0,
-        c if c < 40_u32.MHz::<1, 1>() => 1,
-        c if c < 60_u32.MHz::<1, 1>() => 2,
-        c if c < 80_u32.MHz::<1, 1>() => 3,
-        c if c < 100_u32.MHz::<1, 1>() => 4,
-        c if c < 123_u32.MHz::<1, 1>() => 5,
-        _ => panic!(
-            "Invalid frequency provided to get_flash_wait_states(): {} ",
-            clock_frequency
-        ),
-    }
-}
-
 #[cfg(any(feature = "atsam4e", feature = "atsam4n"))]
 fn set_flash_wait_states_to_maximum(efc: &EFC) {
     efc.fmr
@@ -286,21 +792,17 @@ fn set_flash_wait_states_to_maximum(efc0: &EFC0, efc1: &EFC1) {
 }
 
 #[cfg(any(feature = "atsam4n", feature = "atsam4e"))]
-fn set_flash_wait_states_to_match_frequency(efc: &EFC, clock_frequency: Hertz) {
-    let wait_state_count = get_flash_wait_states_for_clock_frequency(clock_frequency);
-
+fn set_flash_wait_states(efc: &EFC, wait_state_count: u8) {
     efc.fmr
         .modify(|_, w| unsafe { w.fws().bits(wait_state_count).cloe().set_bit() });
 }
 
 #[cfg(feature = "atsam4s")]
-fn set_flash_wait_states_to_match_frequency(
+fn set_flash_wait_states(
     efc0: &EFC0,
     #[cfg(feature = "atsam4sd")] efc1: &EFC1,
-    clock_frequency: Hertz,
+    wait_state_count: u8,
 ) {
-    let wait_state_count = get_flash_wait_states_for_clock_frequency(clock_frequency);
-
     efc0.fmr
         .modify(|_, w| unsafe { w.fws().bits(wait_state_count).cloe().set_bit() });
     #[cfg(feature = "atsam4sd")]
@@ -308,7 +810,7 @@ fn set_flash_wait_states_to_match_frequency(
         .modify(|_, w| unsafe { w.fws().bits(wait_state_count).cloe().set_bit() });
 }
 
-fn switch_main_clock_to_external_12mhz(pmc: &PMC) {
+fn switch_main_clock_to_external_crystal(pmc: &PMC) {
     // Activate external oscillator
     // As we are clocking the core from internal Fast RC, we keep the bit CKGR_MOR_MOSCRCEN.
     // Main Crystal Oscillator Start-up Time (CKGR_MOR_MOSCXTST) is set to maximum value.
@@ -469,16 +971,33 @@ fn switch_master_clock_to_plla(pmc: &PMC, prescaler: u8) {
     wait_for_master_clock_ready(pmc);
 
     // Set the master clock source to PLLA
+    set_master_clock_source(pmc, MasterClockSource::PllaClock);
+
+    wait_for_master_clock_ready(pmc);
+}
+
+fn switch_master_clock_without_pll(pmc: &PMC, source: MasterClockSource, prescaler: u8) {
+    // When switching to the slow clock or main clock, the source must be changed
+    // before the prescaler (ATSAM4E 30.13, ATSAM4S 29.13)
+    set_master_clock_source(pmc, source);
+
+    wait_for_master_clock_ready(pmc);
+
+    // Set the master clock prescaler
+    pmc.pmc_mckr.modify(|_, w| w.pres().bits(prescaler));
+
+    wait_for_master_clock_ready(pmc);
+}
+
+fn set_master_clock_source(pmc: &PMC, source: MasterClockSource) {
     // BUGBUG: What requires the 'unsafe' on SAM4?  SVD issue?
-    let clock_source: u8 = 2; // 2 = PLLA
+    let clock_source: u8 = source.css();
     #[cfg(any(feature = "atsam4e", feature = "atsam4n"))]
     pmc.pmc_mckr
         .modify(|_, w| unsafe { w.css().bits(clock_source) });
 
     #[cfg(feature = "atsam4s")]
     pmc.pmc_mckr.modify(|_, w| w.css().bits(clock_source));
-
-    wait_for_master_clock_ready(pmc);
 }
 
 fn is_master_clock_ready(pmc: &PMC) -> bool {
@@ -489,7 +1008,7 @@ fn wait_for_master_clock_ready(pmc: &PMC) {
     while !is_master_clock_ready(pmc) {}
 }
 
-#[cfg(all(feature = "atsam4s", feature = "usb"))]
+#[cfg(feature = "atsam4s")]
 fn enable_pllb_clock(pmc: &PMC, multiplier: u16, divider: u8) {
     disable_pllb_clock(pmc);
 
@@ -521,17 +1040,17 @@ pub fn reenable_pllb_clock(pmc: &PMC) {
         .modify(|_, w| unsafe { w.mulb().bits(PLLB_MULTIPLIER) });
 }
 
-#[cfg(all(feature = "atsam4s", feature = "usb"))]
+#[cfg(feature = "atsam4s")]
 pub fn disable_pllb_clock(pmc: &PMC) {
     pmc.ckgr_pllbr.modify(|_, w| unsafe { w.mulb().bits(0) });
 }
 
-#[cfg(all(feature = "atsam4s", feature = "usb"))]
+#[cfg(feature = "atsam4s")]
 fn is_pllb_locked(pmc: &PMC) -> bool {
     pmc.pmc_sr.read().lockb().bit_is_set()
 }
 
-#[cfg(all(feature = "atsam4s", feature = "usb"))]
+#[cfg(feature = "atsam4s")]
 pub fn wait_for_pllb_lock(pmc: &PMC) {
     while !is_pllb_locked(pmc) {}
 }
@@ -548,6 +1067,46 @@ pub struct PeripheralClock<STATE> {
     _state: PhantomData<STATE>,
 }
 
+/// Enabled peripheral clocks (see [`PeripheralClocks::power_report`])
+///
+/// ```
+/// let report = clocks.peripheral_clocks.power_report();
+/// defmt::info!("{}", report); // e.g. PowerReport(...): pio_a pio_b uart_0 spi
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerReport {
+    enabled_mask: u64,
+}
+
+impl PowerReport {
+    /// Bit n is set if the clock of peripheral identifier n is enabled
+    pub fn enabled_mask(&self) -> u64 {
+        self.enabled_mask
+    }
+
+    /// Number of enabled peripheral clocks
+    pub fn enabled_count(&self) -> u32 {
+        self.enabled_mask.count_ones()
+    }
+
+    /// Names of the enabled peripheral clocks
+    pub fn enabled(&self) -> impl Iterator<Item = &'static str> + '_ {
+        PERIPHERAL_CLOCK_NAMES
+            .iter()
+            .filter(move |(id, _)| self.enabled_mask & (1 << id) != 0)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl defmt::Format for PowerReport {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "PowerReport({=u64:#018x}):", self.enabled_mask);
+        for name in self.enabled() {
+            defmt::write!(f, " {=str}", name);
+        }
+    }
+}
+
 macro_rules! peripheral_clocks {
     (
         $($PeripheralType:ident, $peripheral_ident:ident, $i:expr,)+
@@ -567,8 +1126,33 @@ macro_rules! peripheral_clocks {
                     )+
                 }
             }
+
+            /// Peripheral clocks currently enabled (PMC_PCSR0 and PMC_PCSR1)
+            /// Bit n is set if the clock of peripheral identifier n is enabled
+            pub fn enabled_mask(&self) -> u64 {
+                let pmc = unsafe { &*PMC::ptr() };
+                #[cfg(not(feature = "atsam4n"))]
+                let pcsr1 = pmc.pmc_pcsr1.read().bits() as u64;
+                #[cfg(feature = "atsam4n")]
+                let pcsr1 = 0;
+                pmc.pmc_pcsr0.read().bits() as u64 | pcsr1 << 32
+            }
+
+            /// Summary of the enabled peripheral clocks, used to find peripherals that can be gated
+            pub fn power_report(&self) -> PowerReport {
+                PowerReport {
+                    enabled_mask: self.enabled_mask(),
+                }
+            }
         }
 
+        // (peripheral identifier, name)
+        const PERIPHERAL_CLOCK_NAMES: &[(u8, &str)] = &[
+            $(
+                ($i, stringify!($peripheral_ident)),
+            )+
+        ];
+
         $(
             #[derive(Default)]
             pub struct $PeripheralType<STATE> {
@@ -633,10 +1217,6 @@ macro_rules! peripheral_clocks {
                     unsafe {self.pcdr0().write_with_zero(|w| w.bits(1 << shift) )};
                     $PeripheralType { _state: PhantomData }
                 }
-
-                pub fn frequency(&self) -> Hertz {
-                    get_master_clock_frequency()
-                }
             }
         )+
     }
@@ -900,14 +1480,146 @@ peripheral_clocks!(
     34,
 );
 
+/// Programmable clock source (PMC_PCKx.CSS)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum ProgrammableClockSource {
+    SlowClock = 0,
+    MainClock = 1,
+    PllaClock = 2,
+    #[cfg(feature = "atsam4s")]
+    PllbClock = 3,
+    MasterClock = 4,
+}
+
+/// Programmable clock prescaler (PMC_PCKx.PRES)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum ProgrammableClockPrescaler {
+    Div1 = 0,
+    Div2 = 1,
+    Div4 = 2,
+    Div8 = 3,
+    Div16 = 4,
+    Div32 = 5,
+    Div64 = 6,
+}
+
+/// Pins that can output programmable clock N (PCKN)
+pub trait ProgrammableClockPin<const N: u8> {}
+
+impl ProgrammableClockPin<0> for Pa6<PfB> {}
+impl ProgrammableClockPin<0> for Pb13<PfB> {}
+impl ProgrammableClockPin<1> for Pa17<PfB> {}
+impl ProgrammableClockPin<1> for Pa21<PfB> {}
+impl ProgrammableClockPin<2> for Pa18<PfB> {}
+impl ProgrammableClockPin<2> for Pa31<PfB> {}
+impl ProgrammableClockPin<2> for Pb3<PfB> {}
+
+/// Programmable clock output N (PCK0 to PCK2)
+///
+/// ```
+/// let pck0 = clocks.programmable_clocks.pck0.enable(
+///     pins.pa6.into_peripheral_function_b(&peripherals.MATRIX),
+///     ProgrammableClockSource::MainClock,
+///     ProgrammableClockPrescaler::Div1,
+/// );
+/// defmt::info!("PCK0: {}", pck0.frequency());
+/// ```
+pub struct ProgrammableClock<const N: u8> {
+    _private: (),
+}
+
+/// Programmable clock N, enabled and routed to a pin
+pub struct EnabledProgrammableClock<const N: u8, PIN> {
+    pin: PIN,
+    frequency: Hertz,
+}
+
+pub struct ProgrammableClocks {
+    pub pck0: ProgrammableClock<0>,
+    pub pck1: ProgrammableClock<1>,
+    pub pck2: ProgrammableClock<2>,
+}
+
+impl ProgrammableClocks {
+    fn new() -> Self {
+        ProgrammableClocks {
+            pck0: ProgrammableClock { _private: () },
+            pck1: ProgrammableClock { _private: () },
+            pck2: ProgrammableClock { _private: () },
+        }
+    }
+}
+
+impl<const N: u8> ProgrammableClock<N> {
+    fn pmc(&self) -> &pmc::RegisterBlock {
+        unsafe { &*PMC::ptr() }
+    }
+
+    /// Configures the source and prescaler, then enables the clock output on the pin
+    /// Blocks until the clock is ready (PMC_SR.PCKRDYx)
+    pub fn enable<PIN: ProgrammableClockPin<N>>(
+        self,
+        pin: PIN,
+        source: ProgrammableClockSource,
+        prescaler: ProgrammableClockPrescaler,
+    ) -> EnabledProgrammableClock<N, PIN> {
+        let pmc = self.pmc();
+
+        // The clock must be disabled before changing the source or prescaler
+        unsafe {
+            pmc.pmc_scdr.write_with_zero(|w| w.bits(1 << (8 + N))); // Disable PCKx
+            pmc.pmc_pck[N as usize]
+                .write_with_zero(|w| w.bits(source as u32 | (prescaler as u32) << 4)); // Select source and prescaler
+            pmc.pmc_scer.write_with_zero(|w| w.bits(1 << (8 + N))); // Enable PCKx
+        }
+        while pmc.pmc_sr.read().bits() & (1 << (8 + N)) == 0 {} // Wait for PCKRDYx
+
+        let source_frequency = match source {
+            ProgrammableClockSource::SlowClock => SLOW_CLOCK_FREQUENCY.Hz(),
+            ProgrammableClockSource::MainClock => calculate_main_clock_frequency(pmc),
+            ProgrammableClockSource::PllaClock => calculate_plla_frequency(pmc),
+            #[cfg(feature = "atsam4s")]
+            ProgrammableClockSource::PllbClock => calculate_pllb_frequency(pmc),
+            ProgrammableClockSource::MasterClock => calculate_master_clock_frequency(pmc),
+        };
+
+        EnabledProgrammableClock {
+            pin,
+            frequency: (source_frequency.raw() >> prescaler as u32).Hz(),
+        }
+    }
+}
+
+impl<const N: u8, PIN> EnabledProgrammableClock<N, PIN> {
+    /// Output frequency, computed when the clock was enabled
+    pub fn frequency(&self) -> Hertz {
+        self.frequency
+    }
+
+    /// Stops the clock output and releases the pin
+    pub fn disable(self) -> (ProgrammableClock<N>, PIN) {
+        unsafe {
+            (*PMC::ptr())
+                .pmc_scdr
+                .write_with_zero(|w| w.bits(1 << (8 + N))); // Disable PCKx
+        }
+        (ProgrammableClock { _private: () }, self.pin)
+    }
+}
+
 pub struct ClockController {
     pub peripheral_clocks: PeripheralClocks,
+    pub programmable_clocks: ProgrammableClocks,
     pub pmc: PMC,
-    master_clock: Hertz,
-    slow_clock: Hertz,
+    pub clocks: Clocks,
+    config: ClockConfig,
 }
 
 impl ClockController {
+    /// Configures the clocks using the fastest master clock supported by the device
+    /// USB is configured when the usb feature is enabled and a crystal is used
+    ///
+    /// Panics if the clock configuration cannot be generated (see [`ClockController::with_config`])
     pub fn new(
         pmc: PMC,
         supc: &SUPC,
@@ -917,6 +1629,36 @@ impl ClockController {
         main_clock: MainClock,
         slow_clock: SlowClock,
     ) -> Self {
+        match Self::with_config(
+            pmc,
+            supc,
+            #[cfg(any(feature = "atsam4e", feature = "atsam4n"))]
+            efc,
+            #[cfg(feature = "atsam4s")]
+            efc0,
+            #[cfg(feature = "atsam4sd")]
+            efc1,
+            main_clock.into(),
+            slow_clock,
+        ) {
+            Ok(clocks) => clocks,
+            Err(err) => panic!("Unsupported clock configuration: {:?}", err),
+        }
+    }
+
+    /// Configures the clocks using computed PLL settings
+    /// The configuration is solved before any registers are modified.
+    pub fn with_config(
+        pmc: PMC,
+        supc: &SUPC,
+        #[cfg(any(feature = "atsam4e", feature = "atsam4n"))] efc: &EFC,
+        #[cfg(feature = "atsam4s")] efc0: &EFC0,
+        #[cfg(feature = "atsam4sd")] efc1: &EFC1,
+        config: ClockConfig,
+        slow_clock: SlowClock,
+    ) -> Result<Self, ClockError> {
+        let solution = config.solve()?;
+
         // Make sure write protection has been disabled
         pmc.pmc_wpmr
             .modify(|_, w| w.wpkey().passwd().wpen().clear_bit());
@@ -930,71 +1672,206 @@ impl ClockController {
             efc1,
         );
         let slow_clock_frequency = setup_slow_clock(supc, slow_clock);
-        let master_clock_frequency = setup_main_clock(&pmc, main_clock);
-        set_flash_wait_states_to_match_frequency(
+        let master_clock_frequency = setup_main_clock(&pmc, config.main_clock, &solution);
+        set_flash_wait_states(
             #[cfg(any(feature = "atsam4e", feature = "atsam4n"))]
             efc,
             #[cfg(feature = "atsam4s")]
             efc0,
             #[cfg(feature = "atsam4sd")]
             efc1,
+            solution.flash_wait_states,
+        );
+
+        setup_usb_clock(&pmc, &solution);
+
+        let clocks = Clocks::new(
+            config.main_clock.frequency(),
+            slow_clock_frequency,
             master_clock_frequency,
+            &solution,
         );
 
-        // Setup USB clock
-        #[cfg(feature = "usb")]
-        match main_clock {
-            // TODO (HaaTa): Does USB even work without a crystal oscillator?
-            //               The bootloader requires an external oscillator for USB to work.
-            #[cfg(not(feature = "atsam4n"))]
-            MainClock::RcOscillator4Mhz => {}
-            MainClock::RcOscillator8Mhz => {}
-            MainClock::RcOscillator12Mhz => {}
-            MainClock::Crystal12Mhz => {
-                // PLLA
-                // 240 MHz / 5 = 48 MHz
-                // This works for both sam4s and sam4e as sam4e only has 1 pll (sam4s has 2)
-                // However, using plla and pllb, lower current usage can be achieved on sam4s
-                // Per the datasheet ~1 mA
-                // NOTE: the datasheet indicates divider is USBDIV + 1
-                #[cfg(feature = "atsam4e")]
-                {
-                    let usbdiv = 5;
-                    pmc.pmc_usb
-                        .modify(|_, w| unsafe { w.usbdiv().bits(usbdiv - 1) });
-                }
+        Ok(ClockController {
+            peripheral_clocks: PeripheralClocks::new(),
+            programmable_clocks: ProgrammableClocks::new(),
+            pmc,
+            clocks,
+            config,
+        })
+    }
 
-                // Use PLLB for sam4s
-                // 96 MHz / 2 = 48 MHz
-                // NOTE: the datasheet indicates divider is USBDIV + 1
-                #[cfg(feature = "atsam4s")]
-                {
-                    wait_for_pllb_lock(&pmc);
+    /// Changes the clock configuration at runtime
+    ///
+    /// The flash wait states are raised to the maximum while the clocks are switched and the
+    /// master clock runs from the main clock while PLLA is reprogrammed.
+    /// Returns the new clock frequencies, drivers that were created using the previous
+    /// frequencies must be notified (see [`ClockChangeListener`]).
+    /// On error the current configuration is left untouched.
+    pub fn reconfigure(
+        &mut self,
+        #[cfg(any(feature = "atsam4e", feature = "atsam4n"))] efc: &EFC,
+        #[cfg(feature = "atsam4s")] efc0: &EFC0,
+        #[cfg(feature = "atsam4sd")] efc1: &EFC1,
+        config: ClockConfig,
+    ) -> Result<Clocks, ClockError> {
+        let solution = config.solve()?;
 
-                    let usbdiv = 2;
-                    pmc.pmc_usb
-                        .modify(|_, w| unsafe { w.usbs().set_bit().usbdiv().bits(usbdiv - 1) });
-                }
-            }
+        set_flash_wait_states_to_maximum(
+            #[cfg(any(feature = "atsam4e", feature = "atsam4n"))]
+            efc,
+            #[cfg(feature = "atsam4s")]
+            efc0,
+            #[cfg(feature = "atsam4sd")]
+            efc1,
+        );
+
+        // PLLA cannot be reprogrammed while it is used as the master clock
+        if self.pmc.pmc_mckr.read().css().bits() == MasterClockSource::PllaClock.css() {
+            switch_master_clock_without_pll(&self.pmc, MasterClockSource::MainClock, 0);
         }
 
-        unsafe {
-            MASTER_CLOCK_FREQUENCY = master_clock_frequency;
+        let master_clock_frequency = setup_main_clock(&self.pmc, config.main_clock, &solution);
+        set_flash_wait_states(
+            #[cfg(any(feature = "atsam4e", feature = "atsam4n"))]
+            efc,
+            #[cfg(feature = "atsam4s")]
+            efc0,
+            #[cfg(feature = "atsam4sd")]
+            efc1,
+            solution.flash_wait_states,
+        );
+
+        setup_usb_clock(&self.pmc, &solution);
+
+        self.clocks = Clocks::new(
+            config.main_clock.frequency(),
+            self.clocks.slow_clock,
+            master_clock_frequency,
+            &solution,
+        );
+        self.config = config;
+
+        Ok(self.clocks)
+    }
+
+    /// Clocks with the master clock frequency replaced by the measured one
+    ///
+    /// When running from the internal fast RC oscillator, this can be used to trim baud rates.
+    ///
+    /// ```
+    /// uart.clocks_changed(controller.trimmed_clocks());
+    /// ```
+    pub fn trimmed_clocks(&self) -> Clocks {
+        self.clocks.with_master_clock(self.measure_master_clock())
+    }
+
+    /// Measures the real main clock frequency (MAINCK) against the slow clock
+    ///
+    /// Uses the main clock frequency counter (CKGR_MCFR), which counts the main clock cycles
+    /// during 16 slow clock periods. The result can be compared to the expected frequency to
+    /// detect a missing or wrong crystal.
+    /// NOTE: The accuracy is limited by the slow clock, the 32 kHz RC oscillator is not trimmed.
+    pub fn measure_main_clock(&self) -> Hertz {
+        measure_main_clock_frequency(&self.pmc, self.clocks.slow_clock)
+    }
+
+    /// Master clock frequency derived from the measured main clock frequency
+    pub fn measure_master_clock(&self) -> Hertz {
+        // The main clock is not used when running from the slow clock
+        if self.pmc.pmc_mckr.read().css().bits() == MasterClockSource::SlowClock.css() {
+            return self.clocks.master_clock;
         }
+        let nominal_main_clock = calculate_main_clock_frequency(&self.pmc);
+        let measured_main_clock = self.measure_main_clock();
 
-        ClockController {
-            peripheral_clocks: PeripheralClocks::new(),
-            pmc,
-            master_clock: master_clock_frequency,
-            slow_clock: slow_clock_frequency,
+        Hertz::from_raw(
+            (self.clocks.master_clock.raw() as u64 * measured_main_clock.raw() as u64
+                / nominal_main_clock.raw() as u64) as u32,
+        )
+    }
+
+    /// Enables the main crystal oscillator clock failure detector
+    ///
+    /// When the crystal stops, the PMC automatically switches the main clock to the fast RC
+    /// oscillator. Call [`ClockController::recover_from_clock_failure`] once a failure has
+    /// been detected (see [`ClockController::enable_clock_failure_interrupt`]).
+    pub fn enable_clock_failure_detector(&mut self) {
+        self.pmc
+            .ckgr_mor
+            .modify(|_, w| w.key().passwd().cfden().set_bit());
+    }
+
+    /// Disables the main crystal oscillator clock failure detector
+    pub fn disable_clock_failure_detector(&mut self) {
+        self.pmc
+            .ckgr_mor
+            .modify(|_, w| w.key().passwd().cfden().clear_bit());
+    }
+
+    /// Enables the PMC interrupt on a clock failure event (CFDEV)
+    pub fn enable_clock_failure_interrupt(&mut self) {
+        unsafe { self.pmc.pmc_ier.write_with_zero(|w| w.cfdev().set_bit()) };
+    }
+
+    /// Disables the PMC interrupt on a clock failure event (CFDEV)
+    pub fn disable_clock_failure_interrupt(&mut self) {
+        unsafe { self.pmc.pmc_idr.write_with_zero(|w| w.cfdev().set_bit()) };
+    }
+
+    /// Returns `true` if a clock failure occurred since the last status read (CFDEV) or if the
+    /// crystal oscillator is currently failing (CFDS)
+    /// NOTE: Reading the status register clears CFDEV
+    pub fn clock_failure_detected(&self) -> bool {
+        let sr = self.pmc.pmc_sr.read();
+        sr.cfdev().bit_is_set() || sr.cfds().bit_is_set()
+    }
+
+    /// Brings the clock configuration back to a known state after a clock failure
+    ///
+    /// The master clock is moved to the main clock, which now runs from the fast RC oscillator,
+    /// and PLLA (and PLLB on SAM4S) is stopped as its input is no longer valid. The USB clock is
+    /// lost.
+    /// Returns the new clock frequencies, or None if no failure was detected.
+    /// Drivers must be notified of the new frequencies (see [`ClockChangeListener`]).
+    pub fn recover_from_clock_failure(&mut self) -> Option<Clocks> {
+        if !self.clock_failure_detected() {
+            return None;
+        }
+
+        // The PMC has already switched to the fast RC, make sure the registers reflect it
+        switch_to_fast_rc_oscillator(&self.pmc);
+        if self.pmc.pmc_mckr.read().css().bits() == MasterClockSource::PllaClock.css() {
+            let prescaler = self.pmc.pmc_mckr.read().pres().bits();
+            switch_master_clock_without_pll(&self.pmc, MasterClockSource::MainClock, prescaler);
         }
+        disable_plla_clock(&self.pmc);
+        #[cfg(feature = "atsam4s")]
+        disable_pllb_clock(&self.pmc);
+
+        self.clocks = Clocks {
+            master_clock: calculate_master_clock_frequency(&self.pmc),
+            main_clock: calculate_main_clock_frequency(&self.pmc),
+            plla: None,
+            pllb: None,
+            usb: None,
+            ..self.clocks
+        };
+
+        Some(self.clocks)
+    }
+
+    /// Configuration last applied using [`ClockController::with_config`] or
+    /// [`ClockController::reconfigure`]
+    pub fn config(&self) -> ClockConfig {
+        self.config
     }
 
     pub fn master_clock(self) -> Hertz {
-        self.master_clock
+        self.clocks.master_clock
     }
 
     pub fn slow_clock(self) -> Hertz {
-        self.slow_clock
+        self.clocks.slow_clock
     }
 }
@@ -15,12 +15,12 @@ pub struct Delay {
 
 impl Delay {
     /// Configures the system timer (SysTick) as a delay provider
-    pub fn new(mut syst: SYST) -> Self {
+    pub fn new(mut syst: SYST, clocks: Clocks) -> Self {
         syst.set_clock_source(SystClkSource::Core);
 
         Delay {
             syst,
-            sysclock: get_master_clock_frequency(),
+            sysclock: clocks.master_clock(),
         }
     }
 
@@ -30,6 +30,12 @@ impl Delay {
     }
 }
 
+impl ClockChangeListener for Delay {
+    fn clocks_changed(&mut self, clocks: Clocks) {
+        self.sysclock = clocks.master_clock();
+    }
+}
+
 impl DelayMs<u32> for Delay {
     fn delay_ms(&mut self, ms: u32) {
         self.delay_us(ms * 1_000);
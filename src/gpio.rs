@@ -2,10 +2,17 @@
 use {
     core::convert::Infallible,
     core::marker::PhantomData,
+    core::sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    fugit::MicrosDurationU32 as MicrosDuration,
     hal::digital::v2::{toggleable, InputPin, IoPin, OutputPin, PinState, StatefulOutputPin},
     paste::paste,
 };
 
+use crate::clock::SLOW_CLOCK_FREQUENCY;
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+use {crate::pdc::*, core::sync::atomic::compiler_fence, embedded_dma::WriteBuffer};
+
 #[cfg(feature = "atsam4e")]
 use {
     crate::clock::PioDClock,
@@ -75,6 +82,171 @@ impl Ports {
     }
 }
 
+impl Ports {
+    /// Enables the write protection of every PIO controller
+    ///
+    /// While locked, writes to the configuration registers (pin mode, pull resistors, filters,
+    /// multi-drive, interrupt modes...) are ignored and recorded as violations. Setting and
+    /// clearing outputs and enabling/disabling interrupts are still possible.
+    ///
+    /// NOTE: Pin mode conversions (e.g. `into_push_pull_output()`) remain callable while locked,
+    /// they change the type of the pin but leave the hardware untouched.
+    ///
+    /// The returned token is required to unlock the configuration. Returns `None` if the
+    /// configuration is already locked.
+    pub fn lock_configuration() -> Option<ConfigurationLock> {
+        if CONFIGURATION_LOCKED.swap(true, Ordering::Acquire) {
+            return None;
+        }
+        for port in Port::ALL {
+            port.write_protection_status(); // Discard violations recorded before locking
+            port.set_write_protection(true);
+        }
+        Some(ConfigurationLock { _private: () })
+    }
+}
+
+impl Ports {
+    /// Reads the configuration of every PIO controller
+    ///
+    /// ```
+    /// let state = Ports::snapshot();
+    ///
+    /// // Park the pins in a low leakage state
+    /// let mut parked = state;
+    /// parked.port_mut(Port::PIOA).pull_up = 0;
+    /// Ports::restore(&parked).unwrap();
+    ///
+    /// power.wait(&mut clocks, &peripherals.EFC, fast_startup).unwrap();
+    ///
+    /// Ports::restore(&state).unwrap();
+    /// ```
+    pub fn snapshot() -> PortState {
+        let mut state = PortState {
+            ports: [PioState::default(); Port::ALL.len()],
+        };
+        for &port in Port::ALL {
+            state.ports[port as usize] = port.snapshot();
+        }
+        state
+    }
+
+    /// Reapplies a configuration read by `snapshot()`
+    ///
+    /// The pin types are not updated, pins converted between `snapshot()` and `restore()` will
+    /// no longer match their hardware configuration.
+    ///
+    /// Returns an error without writing anything if the write protection of any PIO controller
+    /// is enabled (see `Ports::lock_configuration()`).
+    pub fn restore(state: &PortState) -> Result<(), PortStateError> {
+        if Port::ALL.iter().any(|port| port.is_write_protected()) {
+            return Err(PortStateError::ConfigurationLocked);
+        }
+        for &port in Port::ALL {
+            port.restore(state.port(port));
+        }
+        Ok(())
+    }
+}
+
+/// Configuration of a PIO controller, each field is a bit mask of the port's pins
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub struct PioState {
+    /// Pins controlled by the PIO (PSR), others are controlled by a peripheral
+    pub pio_enabled: u32,
+    /// Output drivers enabled (OSR)
+    pub output_enabled: u32,
+    /// Output levels (ODSR)
+    pub output_data: u32,
+    /// Outputs writable through ODSR (OWSR)
+    pub output_write_enabled: u32,
+    /// Pull-ups enabled
+    pub pull_up: u32,
+    /// Pull-downs enabled
+    pub pull_down: u32,
+    /// Open-drain/multi-drive enabled (MDSR)
+    pub multi_drive: u32,
+    /// Input glitch or debounce filters enabled (IFSR)
+    pub input_filter: u32,
+    /// Filters in debounce mode (IFSCSR), others are glitch filters
+    pub debounce: u32,
+    /// Debounce slow clock divider (SCDR)
+    pub debounce_divider: u32,
+    /// Peripheral function selection (ABCDSR1 and ABCDSR2)
+    pub peripheral_select: [u32; 2],
+    /// Schmitt triggers disabled (SCHMITT)
+    #[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+    pub schmitt_disabled: u32,
+}
+
+/// `Ports::restore()` errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum PortStateError {
+    /// The PIO configuration is write protected
+    ConfigurationLocked,
+}
+
+/// Configuration of every PIO controller, see `Ports::snapshot()`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct PortState {
+    ports: [PioState; Port::ALL.len()],
+}
+
+impl PortState {
+    /// Configuration of one port
+    pub fn port(&self, port: Port) -> &PioState {
+        &self.ports[port as usize]
+    }
+
+    /// Configuration of one port, for modifying before `Ports::restore()`
+    pub fn port_mut(&mut self, port: Port) -> &mut PioState {
+        &mut self.ports[port as usize]
+    }
+}
+
+/// Write access to a protected PIO register while the configuration was locked
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct WriteProtectViolation {
+    pub port: Port,
+    /// Offset of the register that was written (WPVSRC)
+    pub register_offset: u16,
+}
+
+/// Set while a `ConfigurationLock` exists
+static CONFIGURATION_LOCKED: AtomicBool = AtomicBool::new(false);
+
+/// Proof that the PIO configuration is locked, see `Ports::lock_configuration()`
+pub struct ConfigurationLock {
+    _private: (),
+}
+
+impl ConfigurationLock {
+    /// Disables the write protection of every PIO controller
+    pub fn unlock(self) {
+        for port in Port::ALL {
+            port.set_write_protection(false);
+        }
+        CONFIGURATION_LOCKED.store(false, Ordering::Release);
+    }
+
+    /// Returns the last violation of each PIO controller since the previous call
+    pub fn violations(&self) -> impl Iterator<Item = WriteProtectViolation> {
+        Port::ALL.iter().filter_map(|&port| {
+            let wpsr = port.write_protection_status();
+            (wpsr & 1 != 0).then_some(WriteProtectViolation {
+                port,
+                register_offset: (wpsr >> 8) as u16, // WPVSRC
+            })
+        })
+    }
+
+    /// I/O lines of `port` whose configuration is locked until the next hardware reset
+    #[cfg(feature = "atsam4e")]
+    pub fn locked_lines(&self, port: Port) -> u32 {
+        port.locked_lines()
+    }
+}
+
 /// Represents a pin configured for input.
 /// The MODE type is typically one of `Floating`, `PullDown` or
 /// `PullUp`.
@@ -112,7 +284,37 @@ pub struct PullUp;
 /// Totem Pole aka Push-Pull
 pub struct PushPull;
 /// Open drain output
+///
+/// `Input<OpenDrain>` is a released open drain line, see the `IoPin` implementations.
 pub struct OpenDrain;
+/// Open drain output with the pull-up enabled
+///
+/// `Input<OpenDrainPullUp>` is a released open drain line held high by the pull-up.
+pub struct OpenDrainPullUp;
+
+/// Computes the SCDR DIV value for a debounce period
+///
+/// The debounce filter rejects pulses shorter than `2 * (DIV + 1)` slow clock periods.
+fn debounce_divider(period: MicrosDuration) -> u16 {
+    let slow_clock_periods =
+        (period.to_micros() as u64 * SLOW_CLOCK_FREQUENCY as u64).div_ceil(1_000_000);
+    (slow_clock_periods.div_ceil(2).saturating_sub(1)).min(0x3FFF) as u16
+}
+
+/// Input change interrupt trigger
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum InterruptTrigger {
+    /// Any rising or falling edge
+    AnyEdge,
+    /// Rising edge only
+    RisingEdge,
+    /// Falling edge only
+    FallingEdge,
+    /// While the input is high
+    HighLevel,
+    /// While the input is low
+    LowLevel,
+}
 
 macro_rules! pins {
     ([
@@ -355,6 +557,25 @@ macro_rules! pin_sysio {
             ) -> $PinType<Output<OpenDrain>> {
                 self.disable_pin_interrupt();
                 unsafe {
+                    self.pudr().write_with_zero(|w| w.bits(1 << $i)); // Disable pull-up
+                    self.ppddr().write_with_zero(|w| w.bits(1 << $i)); // Disable pull-down
+                    self.mder().write_with_zero(|w| w.bits(1 << $i)); // Enable open-drain/multi-drive
+                    self.oer().write_with_zero(|w| w.bits(1 << $i)); // Enable output mode
+                }
+                self.enable_pin(); // Enable pio mode (disables peripheral control of pin)
+
+                $PinType { _mode: PhantomData }
+            }
+
+            /// Configures the pin to operate as an open drain output with the pull-up enabled
+            pub fn into_open_drain_pull_up(
+                mut self,
+                _matrix: &MATRIX,
+            ) -> $PinType<Output<OpenDrainPullUp>> {
+                self.disable_pin_interrupt();
+                unsafe {
+                    self.ppddr().write_with_zero(|w| w.bits(1 << $i)); // Disable pull-down
+                    self.puer().write_with_zero(|w| w.bits(1 << $i)); // Enable pull-up
                     self.mder().write_with_zero(|w| w.bits(1 << $i)); // Enable open-drain/multi-drive
                     self.oer().write_with_zero(|w| w.bits(1 << $i)); // Enable output mode
                 }
@@ -506,6 +727,26 @@ macro_rules! pin_sysio {
                 self.disable_pin_interrupt();
                 self.disable_system_function(matrix);
                 unsafe {
+                    self.pudr().write_with_zero(|w| w.bits(1 << $i)); // Disable pull-up
+                    self.ppddr().write_with_zero(|w| w.bits(1 << $i)); // Disable pull-down
+                    self.mder().write_with_zero(|w| w.bits(1 << $i)); // Enable open-drain/multi-drive
+                    self.oer().write_with_zero(|w| w.bits(1 << $i)); // Enable output mode
+                }
+                self.enable_pin(); // Enable pio mode (disables peripheral control of pin)
+
+                $PinType { _mode: PhantomData }
+            }
+
+            /// Configures the pin to operate as an open drain output with the pull-up enabled
+            pub fn into_open_drain_pull_up(
+                mut self,
+                matrix: &MATRIX,
+            ) -> $PinType<Output<OpenDrainPullUp>> {
+                self.disable_pin_interrupt();
+                self.disable_system_function(matrix);
+                unsafe {
+                    self.ppddr().write_with_zero(|w| w.bits(1 << $i)); // Disable pull-down
+                    self.puer().write_with_zero(|w| w.bits(1 << $i)); // Enable pull-up
                     self.mder().write_with_zero(|w| w.bits(1 << $i)); // Enable open-drain/multi-drive
                     self.oer().write_with_zero(|w| w.bits(1 << $i)); // Enable output mode
                 }
@@ -551,7 +792,7 @@ macro_rules! pin {
                 unsafe { &(*$PIO::ptr()).pudr }
             }
 
-            pub(crate) fn _ier(&mut self) -> &$pio::IER {
+            pub(crate) fn ier(&mut self) -> &$pio::IER {
                 unsafe { &(*$PIO::ptr()).ier }
             }
 
@@ -611,6 +852,46 @@ macro_rules! pin {
                 unsafe { &(*$PIO::ptr()).ifscdr }
             }
 
+            pub(crate) fn ifer(&mut self) -> &$pio::IFER {
+                unsafe { &(*$PIO::ptr()).ifer }
+            }
+
+            pub(crate) fn ifdr(&mut self) -> &$pio::IFDR {
+                unsafe { &(*$PIO::ptr()).ifdr }
+            }
+
+            pub(crate) fn ifscer(&mut self) -> &$pio::IFSCER {
+                unsafe { &(*$PIO::ptr()).ifscer }
+            }
+
+            pub(crate) fn scdr(&mut self) -> &$pio::SCDR {
+                unsafe { &(*$PIO::ptr()).scdr }
+            }
+
+            pub(crate) fn aimer(&mut self) -> &$pio::AIMER {
+                unsafe { &(*$PIO::ptr()).aimer }
+            }
+
+            pub(crate) fn aimdr(&mut self) -> &$pio::AIMDR {
+                unsafe { &(*$PIO::ptr()).aimdr }
+            }
+
+            pub(crate) fn esr(&mut self) -> &$pio::ESR {
+                unsafe { &(*$PIO::ptr()).esr }
+            }
+
+            pub(crate) fn lsr(&mut self) -> &$pio::LSR {
+                unsafe { &(*$PIO::ptr()).lsr }
+            }
+
+            pub(crate) fn rehlsr(&mut self) -> &$pio::REHLSR {
+                unsafe { &(*$PIO::ptr()).rehlsr }
+            }
+
+            pub(crate) fn fellsr(&mut self) -> &$pio::FELLSR {
+                unsafe { &(*$PIO::ptr()).fellsr }
+            }
+
             pub(crate) fn odsr(&self) -> &$pio::ODSR {
                 unsafe { &(*$PIO::ptr()).odsr }
             }
@@ -627,8 +908,8 @@ macro_rules! pin {
                 unsafe { self.pdr().write_with_zero(|w| w.bits(1 << $i)) };
             }
 
-            fn _enable_pin_interrupt(&mut self) {
-                unsafe { self._ier().write_with_zero(|w| w.bits(1 << $i)) };
+            fn enable_pin_interrupt(&mut self) {
+                unsafe { self.ier().write_with_zero(|w| w.bits(1 << $i)) };
             }
 
             fn disable_pin_interrupt(&mut self) {
@@ -648,6 +929,93 @@ macro_rules! pin {
             }
         }
 
+        paste! {
+            impl<MODE> $PinType<Input<MODE>> {
+                /// Enables the input change interrupt of the pin with the given trigger
+                ///
+                /// Any change flagged before this call is discarded.
+                pub fn enable_interrupt(&mut self, trigger: InterruptTrigger) {
+                    self.disable_pin_interrupt();
+                    unsafe {
+                        match trigger {
+                            InterruptTrigger::AnyEdge => {
+                                self.aimdr().write_with_zero(|w| w.bits(1 << $i)); // Disable additional interrupt modes
+                            }
+                            InterruptTrigger::RisingEdge | InterruptTrigger::FallingEdge => {
+                                self.esr().write_with_zero(|w| w.bits(1 << $i)); // Edge detection
+                                self.aimer().write_with_zero(|w| w.bits(1 << $i)); // Enable additional interrupt modes
+                            }
+                            InterruptTrigger::HighLevel | InterruptTrigger::LowLevel => {
+                                self.lsr().write_with_zero(|w| w.bits(1 << $i)); // Level detection
+                                self.aimer().write_with_zero(|w| w.bits(1 << $i)); // Enable additional interrupt modes
+                            }
+                        }
+                        match trigger {
+                            InterruptTrigger::AnyEdge => {}
+                            InterruptTrigger::RisingEdge | InterruptTrigger::HighLevel => {
+                                self.rehlsr().write_with_zero(|w| w.bits(1 << $i)); // Rising edge / high level
+                            }
+                            InterruptTrigger::FallingEdge | InterruptTrigger::LowLevel => {
+                                self.fellsr().write_with_zero(|w| w.bits(1 << $i)); // Falling edge / low level
+                            }
+                        }
+                    }
+                    self.clear_interrupt();
+                    self.enable_pin_interrupt();
+                }
+
+                /// Enables the input glitch filter, pulses shorter than half a master clock
+                /// period are rejected
+                pub fn with_glitch_filter(mut self) -> Self {
+                    unsafe {
+                        self.ifscdr().write_with_zero(|w| w.bits(1 << $i)); // Glitch filter clocked by the master clock
+                        self.ifer().write_with_zero(|w| w.bits(1 << $i)); // Enable input filter
+                    }
+                    self
+                }
+
+                /// Enables the input debounce filter, pulses shorter than `period` are rejected
+                ///
+                /// The debounce period is derived from the slow clock and is shared by all the
+                /// pins of the port (SCDR), the last configured period applies to every debounced
+                /// pin of the port.
+                pub fn with_debounce(mut self, period: MicrosDuration) -> Self {
+                    let div = debounce_divider(period);
+                    unsafe {
+                        self.scdr().write_with_zero(|w| w.bits(div as u32)); // Slow clock divider for debouncing
+                        self.ifscer().write_with_zero(|w| w.bits(1 << $i)); // Debounce filter clocked by the divided slow clock
+                        self.ifer().write_with_zero(|w| w.bits(1 << $i)); // Enable input filter
+                    }
+                    self
+                }
+
+                /// Disables the input glitch or debounce filter
+                pub fn without_input_filter(mut self) -> Self {
+                    unsafe { self.ifdr().write_with_zero(|w| w.bits(1 << $i)) }; // Disable input filter
+                    self
+                }
+
+                /// Disables the input change interrupt of the pin
+                pub fn disable_interrupt(&mut self) {
+                    self.disable_pin_interrupt();
+                    self.clear_interrupt();
+                }
+
+                /// Returns true if the pin has an unhandled input change
+                ///
+                /// Reading the port's ISR clears the flags of every pin of the port, the flags of
+                /// the other pins are kept until they are cleared by their owners.
+                pub fn is_interrupt_pending(&self) -> bool {
+                    [<$PIO:lower _pending_interrupts>](0) & (1 << $i) != 0
+                }
+
+                /// Clears the pending input change of the pin
+                pub fn clear_interrupt(&mut self) {
+                    [<$PIO:lower _pending_interrupts>](1 << $i);
+                }
+            }
+        }
+
         impl<MODE> OutputPin for $PinType<Output<MODE>> {
             type Error = Infallible;
 
@@ -813,6 +1181,136 @@ macro_rules! pin {
             }
         }
 
+        impl IoPin<$PinType<Input<OpenDrain>>, Self> for $PinType<Output<OpenDrain>> {
+            type Error = Infallible;
+            fn into_input_pin(mut self) -> Result<$PinType<Input<OpenDrain>>, Self::Error> {
+                unsafe {
+                    self.odr().write_with_zero(|w| w.bits(1 << $i)); // Disable output mode
+                    self.pudr().write_with_zero(|w| w.bits(1 << $i)); // Disable pull-up
+                    self.ppddr().write_with_zero(|w| w.bits(1 << $i)); // Disable pull-down
+                }
+
+                Ok($PinType { _mode: PhantomData })
+            }
+            fn into_output_pin(mut self, state: PinState) -> Result<Self, Self::Error> {
+                self.set_state(state).unwrap();
+                Ok(self)
+            }
+        }
+
+        impl IoPin<Self, $PinType<Output<OpenDrain>>> for $PinType<Input<OpenDrain>> {
+            type Error = Infallible;
+            fn into_input_pin(self) -> Result<Self, Self::Error> {
+                Ok(self)
+            }
+            fn into_output_pin(
+                mut self,
+                state: PinState,
+            ) -> Result<$PinType<Output<OpenDrain>>, Self::Error> {
+                unsafe {
+                    // Set the level before enabling the output to avoid a glitch
+                    match state {
+                        PinState::Low => {
+                            self.codr().write_with_zero(|w| w.bits(1 << $i));
+                        }
+                        PinState::High => {
+                            self.sodr().write_with_zero(|w| w.bits(1 << $i));
+                        }
+                    }
+                    self.mder().write_with_zero(|w| w.bits(1 << $i)); // Enable open-drain/multi-drive
+                    self.oer().write_with_zero(|w| w.bits(1 << $i)); // Enable output mode
+                }
+
+                Ok($PinType { _mode: PhantomData })
+            }
+        }
+
+        impl IoPin<$PinType<Input<OpenDrainPullUp>>, Self> for $PinType<Output<OpenDrainPullUp>> {
+            type Error = Infallible;
+            fn into_input_pin(mut self) -> Result<$PinType<Input<OpenDrainPullUp>>, Self::Error> {
+                unsafe {
+                    self.odr().write_with_zero(|w| w.bits(1 << $i)); // Disable output mode (pull-up is already enabled)
+                }
+
+                Ok($PinType { _mode: PhantomData })
+            }
+            fn into_output_pin(mut self, state: PinState) -> Result<Self, Self::Error> {
+                self.set_state(state).unwrap();
+                Ok(self)
+            }
+        }
+
+        impl IoPin<Self, $PinType<Output<OpenDrainPullUp>>> for $PinType<Input<OpenDrainPullUp>> {
+            type Error = Infallible;
+            fn into_input_pin(self) -> Result<Self, Self::Error> {
+                Ok(self)
+            }
+            fn into_output_pin(
+                mut self,
+                state: PinState,
+            ) -> Result<$PinType<Output<OpenDrainPullUp>>, Self::Error> {
+                unsafe {
+                    // Set the level before enabling the output to avoid a glitch
+                    match state {
+                        PinState::Low => {
+                            self.codr().write_with_zero(|w| w.bits(1 << $i));
+                        }
+                        PinState::High => {
+                            self.sodr().write_with_zero(|w| w.bits(1 << $i));
+                        }
+                    }
+                    self.mder().write_with_zero(|w| w.bits(1 << $i)); // Enable open-drain/multi-drive
+                    self.oer().write_with_zero(|w| w.bits(1 << $i)); // Enable output mode
+                }
+
+                Ok($PinType { _mode: PhantomData })
+            }
+        }
+
+        /// Pad configuration
+        ///
+        /// SAM4E and SAM4S pads only have a configurable Schmitt trigger, their PIO has no drive
+        /// strength or slew rate register.
+        #[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+        impl<MODE> $PinType<MODE> {
+            pub(crate) fn schmitt(&self) -> &$pio::SCHMITT {
+                unsafe { &(*$PIO::ptr()).schmitt }
+            }
+
+            /// Enables the input Schmitt trigger of the pad (enabled after reset)
+            pub fn enable_schmitt_trigger(&mut self) {
+                // SCHMITT is shared by all the pins of the port
+                cortex_m::interrupt::free(|_| {
+                    self.schmitt()
+                        .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << $i)) })
+                });
+            }
+
+            /// Disables the input Schmitt trigger of the pad
+            pub fn disable_schmitt_trigger(&mut self) {
+                // SCHMITT is shared by all the pins of the port
+                cortex_m::interrupt::free(|_| {
+                    self.schmitt()
+                        .modify(|r, w| unsafe { w.bits(r.bits() | (1 << $i)) })
+                });
+            }
+
+            /// Returns true if the input Schmitt trigger of the pad is enabled
+            pub fn is_schmitt_trigger_enabled(&self) -> bool {
+                self.schmitt().read().bits() & (1 << $i) == 0
+            }
+        }
+
+        impl<MODE> BusPin for $PinType<Input<MODE>> {
+            type Port = $PIO;
+            const INDEX: u8 = $i;
+        }
+
+        impl<MODE> BusPin for $PinType<Output<MODE>> {
+            type Port = $PIO;
+            const INDEX: u8 = $i;
+        }
+
         paste! {
             impl<MODE> $PinType<MODE> {
                 /// Erases the pin number from the type
@@ -832,6 +1330,25 @@ macro_rules! pin {
                     self.into_generic().downgrade()
                 }
             }
+
+            impl<MODE: PinMode> From<$PinType<MODE>> for DynPin {
+                fn from(pin: $PinType<MODE>) -> Self {
+                    pin.downgrade().into()
+                }
+            }
+
+            impl<MODE: PinMode> TryFrom<DynPin> for $PinType<MODE> {
+                type Error = DynPin;
+
+                /// Returns the DynPin back if it is another pin or is not in mode MODE
+                fn try_from(pin: DynPin) -> Result<Self, DynPin> {
+                    if pin.port == Port::$PIO && pin.i == $i && pin.mode == MODE::DYN {
+                        Ok($PinType { _mode: PhantomData })
+                    } else {
+                        Err(pin)
+                    }
+                }
+            }
         }
     };
 }
@@ -841,6 +1358,45 @@ macro_rules! pin_generic {
         $port:ident
     ) => {
         paste! {
+            /// Input change flags of the port that were read from ISR but not cleared yet
+            static [<$port _PENDING_INTERRUPTS>]: AtomicU32 = AtomicU32::new(0);
+
+            /// Reads the port's ISR (clear on read) and returns every pending input change, then
+            /// clears the changes in `clear`
+            ///
+            /// Every reader of ISR must go through this function, the read and the update of the
+            /// pending flags happen in a critical section so no change can be lost.
+            pub(crate) fn [<$port:lower _pending_interrupts>](clear: u32) -> u32 {
+                cortex_m::interrupt::free(|_| {
+                    let isr = unsafe { (*$port::ptr()).isr.read().bits() };
+                    let pending = [<$port _PENDING_INTERRUPTS>].load(Ordering::Relaxed) | isr;
+                    [<$port _PENDING_INTERRUPTS>].store(pending & !clear, Ordering::Relaxed);
+                    pending
+                })
+            }
+
+            impl PioPort for $port {
+                fn enable_output_write(mask: u32) {
+                    unsafe { (*$port::ptr()).ower.write_with_zero(|w| w.bits(mask)) };
+                }
+
+                fn disable_output_write(mask: u32) {
+                    unsafe { (*$port::ptr()).owdr.write_with_zero(|w| w.bits(mask)) };
+                }
+
+                fn output_data() -> u32 {
+                    unsafe { (*$port::ptr()).odsr.read().bits() }
+                }
+
+                fn write_output_data(value: u32) {
+                    unsafe { (*$port::ptr()).odsr.write_with_zero(|w| w.bits(value)) };
+                }
+
+                fn pin_data() -> u32 {
+                    unsafe { (*$port::ptr()).pdsr.read().bits() }
+                }
+            }
+
             pub struct [<$port Generic>]<MODE> {
                 i: u8,
                 _mode: PhantomData<MODE>,
@@ -1014,24 +1570,104 @@ macro_rules! pin_generic {
                     Ok( [<$port Generic>] { i: self.i, _mode: PhantomData } )
                 }
             }
-        }
-    };
-}
 
-#[cfg(feature = "atsam4e")]
-pins!([
-    Pa0: (pa0, 0, true), // WKUP0
-    Pa1: (pa1, 1, true), // WKUP1
-    Pa2: (pa2, 2, true), // WKUP2
-    Pa3: (pa3, 3, false),
-    Pa4: (pa4, 4, true), // WKUP3
-    Pa5: (pa5, 5, true), // WKUP4
-    Pa6: (pa6, 6, false),
-    Pa7: (pa7, 7, false),
-    Pa8: (pa8, 8, true), // WKUP5
-    Pa9: (pa9, 9, true), // WKUP6
-    Pa10: (pa10, 10, false),
-    Pa11: (pa11, 11, true), // WKUP7
+            impl IoPin<[<$port Generic>]<Input<OpenDrain>>, Self> for [<$port Generic>]<Output<OpenDrain>> {
+                type Error = Infallible;
+                fn into_input_pin(self) -> Result<[<$port Generic>]<Input<OpenDrain>>, Self::Error> {
+                    unsafe {
+                        (*$port::ptr()).odr.write_with_zero(|w| w.bits(1 << self.i)); // Disable output mode
+                        (*$port::ptr()).pudr.write_with_zero(|w| w.bits(1 << self.i)); // Disable pull-up
+                        (*$port::ptr()).ppddr.write_with_zero(|w| w.bits(1 << self.i)); // Disable pull-down
+                    }
+
+                    Ok([<$port Generic>] { i: self.i, _mode: PhantomData })
+                }
+                fn into_output_pin(mut self, state: PinState) -> Result<Self, Self::Error> {
+                    self.set_state(state).unwrap();
+                    Ok(self)
+                }
+            }
+
+            impl IoPin<Self, [<$port Generic>]<Output<OpenDrain>>> for [<$port Generic>]<Input<OpenDrain>> {
+                type Error = Infallible;
+                fn into_input_pin(self) -> Result<Self, Self::Error> {
+                    Ok(self)
+                }
+                fn into_output_pin(self, state: PinState) -> Result<[<$port Generic>]<Output<OpenDrain>>, Self::Error> {
+                    unsafe {
+                        // Set the level before enabling the output to avoid a glitch
+                        match state {
+                            PinState::Low => {
+                                (*$port::ptr()).codr.write_with_zero(|w| w.bits(1 << self.i) );
+                            }
+                            PinState::High => {
+                                (*$port::ptr()).sodr.write_with_zero(|w| w.bits(1 << self.i) );
+                            }
+                        }
+                        (*$port::ptr()).mder.write_with_zero(|w| w.bits(1 << self.i)); // Enable open-drain/multi-drive
+                        (*$port::ptr()).oer.write_with_zero(|w| w.bits(1 << self.i)); // Enable output mode
+                    }
+
+                    Ok([<$port Generic>] { i: self.i, _mode: PhantomData })
+                }
+            }
+
+            impl IoPin<[<$port Generic>]<Input<OpenDrainPullUp>>, Self> for [<$port Generic>]<Output<OpenDrainPullUp>> {
+                type Error = Infallible;
+                fn into_input_pin(self) -> Result<[<$port Generic>]<Input<OpenDrainPullUp>>, Self::Error> {
+                    unsafe {
+                        (*$port::ptr()).odr.write_with_zero(|w| w.bits(1 << self.i)); // Disable output mode (pull-up is already enabled)
+                    }
+
+                    Ok([<$port Generic>] { i: self.i, _mode: PhantomData })
+                }
+                fn into_output_pin(mut self, state: PinState) -> Result<Self, Self::Error> {
+                    self.set_state(state).unwrap();
+                    Ok(self)
+                }
+            }
+
+            impl IoPin<Self, [<$port Generic>]<Output<OpenDrainPullUp>>> for [<$port Generic>]<Input<OpenDrainPullUp>> {
+                type Error = Infallible;
+                fn into_input_pin(self) -> Result<Self, Self::Error> {
+                    Ok(self)
+                }
+                fn into_output_pin(self, state: PinState) -> Result<[<$port Generic>]<Output<OpenDrainPullUp>>, Self::Error> {
+                    unsafe {
+                        // Set the level before enabling the output to avoid a glitch
+                        match state {
+                            PinState::Low => {
+                                (*$port::ptr()).codr.write_with_zero(|w| w.bits(1 << self.i) );
+                            }
+                            PinState::High => {
+                                (*$port::ptr()).sodr.write_with_zero(|w| w.bits(1 << self.i) );
+                            }
+                        }
+                        (*$port::ptr()).mder.write_with_zero(|w| w.bits(1 << self.i)); // Enable open-drain/multi-drive
+                        (*$port::ptr()).oer.write_with_zero(|w| w.bits(1 << self.i)); // Enable output mode
+                    }
+
+                    Ok([<$port Generic>] { i: self.i, _mode: PhantomData })
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "atsam4e")]
+pins!([
+    Pa0: (pa0, 0, true), // WKUP0
+    Pa1: (pa1, 1, true), // WKUP1
+    Pa2: (pa2, 2, true), // WKUP2
+    Pa3: (pa3, 3, false),
+    Pa4: (pa4, 4, true), // WKUP3
+    Pa5: (pa5, 5, true), // WKUP4
+    Pa6: (pa6, 6, false),
+    Pa7: (pa7, 7, false),
+    Pa8: (pa8, 8, true), // WKUP5
+    Pa9: (pa9, 9, true), // WKUP6
+    Pa10: (pa10, 10, false),
+    Pa11: (pa11, 11, true), // WKUP7
     Pa12: (pa12, 12, false),
     Pa13: (pa13, 13, false),
     Pa14: (pa14, 14, true), // WKUP8
@@ -1326,51 +1962,341 @@ pins!([
     Pc31: (pc31, 31, false),
 ], [], []);
 
+/// Defines a structure holding the pins of a board, converted to their initial modes
+///
+/// Besides single pins, peripheral bundles group several pins into a tuple ready to be handed to
+/// a driver constructor. The bundle kind (`uart`, `spi`, ...) only documents the intent. A pin
+/// claimed twice fails to compile with a use of moved value error.
+///
+/// ```
+/// define_pin_map! {
+///     struct Pins,
+///
+///     pin led = a0<Output<PushPull>, into_push_pull_output>,
+///     uart debug = (
+///         a9<PfA, into_peripheral_function_a>,
+///         a10<PfA, into_peripheral_function_a>
+///     ),
+///     spi flash = (
+///         a12<PfA, into_peripheral_function_a>,
+///         a13<PfA, into_peripheral_function_a>,
+///         a14<PfA, into_peripheral_function_a>
+///     ),
+/// }
+///
+/// let pins = Pins::new(ports, &peripherals.MATRIX);
+/// let (rx, tx) = pins.debug;
+/// let serial = Serial0::new(peripherals.UART0, clock, clocks, rx, tx, 115_200, None);
+/// ```
 #[macro_export]
 macro_rules! define_pin_map {
-    ($(#[$topattr:meta])* struct $Type:ident,
-     $( $(#[$attr:meta])* pin $name:ident = $pin_ident:ident<$pin_type:ty, $into_method:ident>),+ , ) => {
+    ($(#[$topattr:meta])* struct $Type:ident, $($items:tt)+) => {
+        $crate::define_pin_map!(@munch [$(#[$topattr])*] $Type [ports pins matrix] [] [] $($items)+);
+    };
+
+    (@munch [$($topattr:tt)*] $Type:ident [$ports:ident $pins:ident $matrix:ident]
+     [$($fields:tt)*] [$($lets:tt)*]
+     $(#[$attr:meta])* pin $name:ident = $pin_ident:ident<$pin_type:ty, $into_method:ident>,
+     $($rest:tt)*) => {
+        $crate::define_pin_map!(@munch [$($topattr)*] $Type [$ports $pins $matrix]
+            [$($fields)* $(#[$attr])* $name: [[<P $pin_ident>]<$pin_type>]]
+            [$($lets)* $name = [$pins.[<p $pin_ident>].$into_method($matrix)]]
+            $($rest)*);
+    };
 
+    (@munch [$($topattr:tt)*] $Type:ident [$ports:ident $pins:ident $matrix:ident]
+     [$($fields:tt)*] [$($lets:tt)*]
+     $(#[$attr:meta])* $kind:ident $name:ident =
+        ($($pin_ident:ident<$pin_type:ty, $into_method:ident>),+ $(,)?),
+     $($rest:tt)*) => {
+        $crate::define_pin_map!(@munch [$($topattr)*] $Type [$ports $pins $matrix]
+            [$($fields)* $(#[$attr])* $name: [($([<P $pin_ident>]<$pin_type>,)+)]]
+            [$($lets)* $name = [($($pins.[<p $pin_ident>].$into_method($matrix),)+)]]
+            $($rest)*);
+    };
+
+    (@munch [$($topattr:tt)*] $Type:ident [$ports:ident $pins:ident $matrix:ident]
+     [$($(#[$attr:meta])* $field:ident: [$($field_type:tt)+])+]
+     [$($name:ident = [$($value:tt)+])+]) => {
         paste! {
-            $(#[$topattr])*
+            $($topattr)*
             pub struct $Type {
                 $(
                     $(#[$attr])*
-                    pub $name: [<P $pin_ident>]<$pin_type>
+                    pub $field: $($field_type)+
                 ),+
             }
-        }
 
-        impl $Type {
-            /// Returns the pins for the device
-            paste! {
-                pub fn new(ports: Ports, matrix: &MATRIX) -> Self {
-                    let pins = ports.split();
+            impl $Type {
+                /// Returns the pins for the device
+                pub fn new($ports: Ports, $matrix: &MATRIX) -> Self {
+                    let $pins = $ports.split();
                     // Create local pins with the correct type so we can put them into the
                     // pin structure below.
                     $(
-                        let [<new_pin $pin_ident>] = pins.[<p $pin_ident>].$into_method(matrix);
+                        let $name = $($value)+;
                     )+
                     $Type {
-                        $(
-                            $name: [<new_pin $pin_ident>]
-                        ),+
+                        $($name),+
                     }
                 }
             }
         }
-    }
+    };
+}
+
+/// Applies a DynPinMode to the pins of `mask` of a PIO register block
+macro_rules! configure_dyn_pin {
+    ($pio:expr, $mask:expr, $mode:expr) => {{
+        let pio = $pio;
+        let mask: u32 = $mask;
+        match $mode {
+            DynPinMode::FloatingInput | DynPinMode::PullDownInput | DynPinMode::PullUpInput => {
+                pio.idr.write_with_zero(|w| w.bits(mask)); // Disable interrupt
+                pio.mddr.write_with_zero(|w| w.bits(mask)); // Disable open-drain/multi-drive
+                pio.odr.write_with_zero(|w| w.bits(mask)); // Disable output mode
+                match $mode {
+                    DynPinMode::PullDownInput => {
+                        pio.pudr.write_with_zero(|w| w.bits(mask)); // Disable pull-up (this must happen first when enabling pull-down resistors)
+                        pio.ppder.write_with_zero(|w| w.bits(mask)); // Enable pull-down
+                    }
+                    DynPinMode::PullUpInput => {
+                        pio.ppddr.write_with_zero(|w| w.bits(mask)); // Disable pull-down
+                        pio.puer.write_with_zero(|w| w.bits(mask)); // Enable pull-up
+                    }
+                    _ => {
+                        pio.pudr.write_with_zero(|w| w.bits(mask)); // Disable pull-up
+                        pio.ppddr.write_with_zero(|w| w.bits(mask)); // Disable pull-down
+                    }
+                }
+                pio.per.write_with_zero(|w| w.bits(mask)); // Enable pio mode (disables peripheral control of pin)
+            }
+            DynPinMode::PushPullOutput
+            | DynPinMode::OpenDrainOutput
+            | DynPinMode::OpenDrainPullUpOutput => {
+                pio.idr.write_with_zero(|w| w.bits(mask)); // Disable interrupt
+                if $mode == DynPinMode::OpenDrainPullUpOutput {
+                    pio.ppddr.write_with_zero(|w| w.bits(mask)); // Disable pull-down
+                    pio.puer.write_with_zero(|w| w.bits(mask)); // Enable pull-up
+                } else if $mode == DynPinMode::OpenDrainOutput {
+                    pio.pudr.write_with_zero(|w| w.bits(mask)); // Disable pull-up
+                    pio.ppddr.write_with_zero(|w| w.bits(mask)); // Disable pull-down
+                }
+                if $mode == DynPinMode::PushPullOutput {
+                    pio.mddr.write_with_zero(|w| w.bits(mask)); // Disable open-drain/multi-drive
+                } else {
+                    pio.mder.write_with_zero(|w| w.bits(mask)); // Enable open-drain/multi-drive
+                }
+                pio.oer.write_with_zero(|w| w.bits(mask)); // Enable output mode
+                pio.per.write_with_zero(|w| w.bits(mask)); // Enable pio mode (disables peripheral control of pin)
+            }
+            DynPinMode::PeripheralA
+            | DynPinMode::PeripheralB
+            | DynPinMode::PeripheralC
+            | DynPinMode::PeripheralD => {
+                pio.pudr.write_with_zero(|w| w.bits(mask)); // Disable Pullup
+                pio.ppddr.write_with_zero(|w| w.bits(mask)); // Disable Pulldown
+                pio.mddr.write_with_zero(|w| w.bits(mask)); // Disable Multi-drive (open drain)
+                pio.ifscdr.write_with_zero(|w| w.bits(mask)); // Disable Glitch filter (Debounce)
+                let (sr1, sr2) = match $mode {
+                    DynPinMode::PeripheralA => (false, false),
+                    DynPinMode::PeripheralB => (true, false),
+                    DynPinMode::PeripheralC => (false, true),
+                    _ => (true, true),
+                };
+                pio.abcdsr[0].modify(|r, w| w.bits(if sr1 { r.bits() | mask } else { r.bits() & !mask })); // Set up peripheral function
+                pio.abcdsr[1].modify(|r, w| w.bits(if sr2 { r.bits() | mask } else { r.bits() & !mask }));
+                pio.pdr.write_with_zero(|w| w.bits(mask)); // Disable pio mode (peripheral control of pin)
+            }
+        }
+    }};
 }
 
 macro_rules! impl_pxx {
     ($(($port:ident)),*) => {
         paste! {
+            /// PIO controller
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+            pub enum Port {
+                $(
+                    $port
+                ),*
+            }
+
+            impl Port {
+                /// All the PIO controllers of the device
+                pub const ALL: &'static [Port] = &[$(Port::$port),*];
+
+                /// Enables or disables the write protection (WPMR)
+                fn set_write_protection(self, enable: bool) {
+                    match self {
+                        $(Port::$port => unsafe {
+                            (*$port::ptr())
+                                .wpmr
+                                .write_with_zero(|w| w.wpkey().bits(0x50494F).wpen().bit(enable)) // "PIO"
+                        }),*
+                    }
+                }
+
+                /// Returns true if the write protection is enabled (WPMR.WPEN)
+                fn is_write_protected(self) -> bool {
+                    match self {
+                        $(Port::$port => unsafe { (*$port::ptr()).wpmr.read().wpen().bit_is_set() }),*
+                    }
+                }
+
+                /// Reads WPSR, the violation status is cleared on read
+                fn write_protection_status(self) -> u32 {
+                    match self {
+                        $(Port::$port => unsafe { (*$port::ptr()).wpsr.read().bits() }),*
+                    }
+                }
+
+                /// I/O lines whose configuration is locked (LOCKSR)
+                #[cfg(feature = "atsam4e")]
+                fn locked_lines(self) -> u32 {
+                    match self {
+                        $(Port::$port => unsafe { (*$port::ptr()).locksr.read().bits() }),*
+                    }
+                }
+
+                /// Reads the configuration of the port
+                fn snapshot(self) -> PioState {
+                    match self {
+                        $(Port::$port => {
+                            let pio = unsafe { &*$port::ptr() };
+                            PioState {
+                                pio_enabled: pio.psr.read().bits(),
+                                output_enabled: pio.osr.read().bits(),
+                                output_data: pio.odsr.read().bits(),
+                                output_write_enabled: pio.owsr.read().bits(),
+                                pull_up: !pio.pusr.read().bits(),
+                                pull_down: !pio.ppdsr.read().bits(),
+                                multi_drive: pio.mdsr.read().bits(),
+                                input_filter: pio.ifsr.read().bits(),
+                                debounce: pio.ifscsr.read().bits(),
+                                debounce_divider: pio.scdr.read().bits(),
+                                peripheral_select: [
+                                    pio.abcdsr[0].read().bits(),
+                                    pio.abcdsr[1].read().bits(),
+                                ],
+                                #[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+                                schmitt_disabled: pio.schmitt.read().bits(),
+                            }
+                        }),*
+                    }
+                }
+
+                /// Reapplies a configuration read by `snapshot()`
+                fn restore(self, state: &PioState) {
+                    match self {
+                        $(Port::$port => unsafe {
+                            let pio = &*$port::ptr();
+                            pio.pudr.write_with_zero(|w| w.bits(!state.pull_up)); // Disable pull-ups (must happen first when enabling pull-down resistors)
+                            pio.ppddr.write_with_zero(|w| w.bits(!state.pull_down));
+                            pio.ppder.write_with_zero(|w| w.bits(state.pull_down));
+                            pio.puer.write_with_zero(|w| w.bits(state.pull_up));
+                            pio.mddr.write_with_zero(|w| w.bits(!state.multi_drive));
+                            pio.mder.write_with_zero(|w| w.bits(state.multi_drive));
+                            pio.scdr.write_with_zero(|w| w.bits(state.debounce_divider));
+                            pio.ifscdr.write_with_zero(|w| w.bits(!state.debounce));
+                            pio.ifscer.write_with_zero(|w| w.bits(state.debounce));
+                            pio.ifdr.write_with_zero(|w| w.bits(!state.input_filter));
+                            pio.ifer.write_with_zero(|w| w.bits(state.input_filter));
+                            #[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+                            pio.schmitt.write_with_zero(|w| w.bits(state.schmitt_disabled));
+                            pio.abcdsr[0].write_with_zero(|w| w.bits(state.peripheral_select[0]));
+                            pio.abcdsr[1].write_with_zero(|w| w.bits(state.peripheral_select[1]));
+                            pio.owdr.write_with_zero(|w| w.bits(!state.output_write_enabled));
+                            pio.ower.write_with_zero(|w| w.bits(state.output_write_enabled));
+                            // Set the levels before enabling the outputs to avoid glitches
+                            pio.codr.write_with_zero(|w| w.bits(!state.output_data));
+                            pio.sodr.write_with_zero(|w| w.bits(state.output_data));
+                            pio.odr.write_with_zero(|w| w.bits(!state.output_enabled));
+                            pio.oer.write_with_zero(|w| w.bits(state.output_enabled));
+                            pio.pdr.write_with_zero(|w| w.bits(!state.pio_enabled));
+                            pio.per.write_with_zero(|w| w.bits(state.pio_enabled));
+                        }),*
+                    }
+                }
+
+                /// Reads the port's ISR and returns every pending input change, then clears the
+                /// changes in `clear`
+                fn pending_interrupts(self, clear: u32) -> u32 {
+                    match self {
+                        $(Port::$port => [<$port:lower _pending_interrupts>](clear)),*
+                    }
+                }
+
+                /// Input changes with the interrupt enabled (IMR)
+                fn interrupt_mask(self) -> u32 {
+                    match self {
+                        $(Port::$port => unsafe { (*$port::ptr()).imr.read().bits() }),*
+                    }
+                }
+            }
+
             pub enum PioX<MODE> {
                 $(
                     $port([<$port Generic>]<MODE>)
                 ),*
             }
 
+            impl<MODE: PinMode> From<PioX<MODE>> for DynPin {
+                fn from(pin: PioX<MODE>) -> Self {
+                    match pin {
+                        $(PioX::$port(pin) => DynPin {
+                            port: Port::$port,
+                            i: pin.i,
+                            mode: MODE::DYN,
+                        }),*
+                    }
+                }
+            }
+
+            impl<MODE: PinMode> TryFrom<DynPin> for PioX<MODE> {
+                type Error = DynPin;
+
+                /// Returns the DynPin back if it is not in mode MODE
+                fn try_from(pin: DynPin) -> Result<Self, DynPin> {
+                    if pin.mode != MODE::DYN {
+                        return Err(pin);
+                    }
+                    match pin.port {
+                        $(Port::$port => Ok(PioX::$port([<$port Generic>] { i: pin.i, _mode: PhantomData }))),*
+                    }
+                }
+            }
+
+            impl DynPin {
+                fn configure(&mut self, mode: DynPinMode) {
+                    match self.port {
+                        $(Port::$port => unsafe { configure_dyn_pin!(&*$port::ptr(), 1 << self.i, mode) }),*
+                    }
+                }
+
+                fn pin_data(&self) -> u32 {
+                    match self.port {
+                        $(Port::$port => unsafe { (*$port::ptr()).pdsr.read().bits() }),*
+                    }
+                }
+
+                fn output_data(&self) -> u32 {
+                    match self.port {
+                        $(Port::$port => unsafe { (*$port::ptr()).odsr.read().bits() }),*
+                    }
+                }
+
+                fn set_output(&mut self, high: bool) {
+                    match (self.port, high) {
+                        $(
+                            (Port::$port, true) => unsafe { (*$port::ptr()).sodr.write_with_zero(|w| w.bits(1 << self.i)) },
+                            (Port::$port, false) => unsafe { (*$port::ptr()).codr.write_with_zero(|w| w.bits(1 << self.i)) },
+                        )*
+                    }
+                }
+            }
+
             impl<MODE> OutputPin for PioX<Output<MODE>> {
                 type Error = Infallible;
                 fn set_high(&mut self) -> Result<(), Infallible> {
@@ -1572,7 +2498,642 @@ macro_rules! impl_pxx {
                     }
                 }
             }
+
+            impl IoPin<PioX<Input<OpenDrain>>, Self> for PioX<Output<OpenDrain>> {
+                type Error = Infallible;
+                fn into_input_pin(self) -> Result<PioX<Input<OpenDrain>>, Self::Error> {
+                    unsafe {
+                        match self {
+                            $(PioX::$port(pin) => {
+                                (*$port::ptr()).odr.write_with_zero(|w| w.bits(1 << pin.i)); // Disable output mode
+                                (*$port::ptr()).pudr.write_with_zero(|w| w.bits(1 << pin.i)); // Disable pull-up
+                                (*$port::ptr()).ppddr.write_with_zero(|w| w.bits(1 << pin.i)); // Disable pull-down
+
+                                Ok(PioX::$port([<$port Generic>] { i: pin.i, _mode: PhantomData }))
+                            })*
+                        }
+                    }
+                }
+                fn into_output_pin(mut self, state: PinState) -> Result<Self, Self::Error> {
+                    self.set_state(state).unwrap();
+                    Ok(self)
+                }
+            }
+
+            impl IoPin<Self, PioX<Output<OpenDrain>>> for PioX<Input<OpenDrain>> {
+                type Error = Infallible;
+                fn into_input_pin(self) -> Result<Self, Self::Error> {
+                    Ok(self)
+                }
+                fn into_output_pin(self, state: PinState) -> Result<PioX<Output<OpenDrain>>, Self::Error> {
+                    unsafe {
+                        match self {
+                            $(PioX::$port(pin) => {
+                                // Set the level before enabling the output to avoid a glitch
+                                match state {
+                                    PinState::Low => {
+                                        (*$port::ptr()).codr.write_with_zero(|w| w.bits(1 << pin.i) );
+                                    }
+                                    PinState::High => {
+                                        (*$port::ptr()).sodr.write_with_zero(|w| w.bits(1 << pin.i) );
+                                    }
+                                }
+                                (*$port::ptr()).mder.write_with_zero(|w| w.bits(1 << pin.i)); // Enable open-drain/multi-drive
+                                (*$port::ptr()).oer.write_with_zero(|w| w.bits(1 << pin.i)); // Enable output mode
+
+                                Ok(PioX::$port([<$port Generic>] { i: pin.i, _mode: PhantomData }))
+                            })*
+                        }
+                    }
+                }
+            }
+
+            impl IoPin<PioX<Input<OpenDrainPullUp>>, Self> for PioX<Output<OpenDrainPullUp>> {
+                type Error = Infallible;
+                fn into_input_pin(self) -> Result<PioX<Input<OpenDrainPullUp>>, Self::Error> {
+                    unsafe {
+                        match self {
+                            $(PioX::$port(pin) => {
+                                (*$port::ptr()).odr.write_with_zero(|w| w.bits(1 << pin.i)); // Disable output mode (pull-up is already enabled)
+
+                                Ok(PioX::$port([<$port Generic>] { i: pin.i, _mode: PhantomData }))
+                            })*
+                        }
+                    }
+                }
+                fn into_output_pin(mut self, state: PinState) -> Result<Self, Self::Error> {
+                    self.set_state(state).unwrap();
+                    Ok(self)
+                }
+            }
+
+            impl IoPin<Self, PioX<Output<OpenDrainPullUp>>> for PioX<Input<OpenDrainPullUp>> {
+                type Error = Infallible;
+                fn into_input_pin(self) -> Result<Self, Self::Error> {
+                    Ok(self)
+                }
+                fn into_output_pin(self, state: PinState) -> Result<PioX<Output<OpenDrainPullUp>>, Self::Error> {
+                    unsafe {
+                        match self {
+                            $(PioX::$port(pin) => {
+                                // Set the level before enabling the output to avoid a glitch
+                                match state {
+                                    PinState::Low => {
+                                        (*$port::ptr()).codr.write_with_zero(|w| w.bits(1 << pin.i) );
+                                    }
+                                    PinState::High => {
+                                        (*$port::ptr()).sodr.write_with_zero(|w| w.bits(1 << pin.i) );
+                                    }
+                                }
+                                (*$port::ptr()).mder.write_with_zero(|w| w.bits(1 << pin.i)); // Enable open-drain/multi-drive
+                                (*$port::ptr()).oer.write_with_zero(|w| w.bits(1 << pin.i)); // Enable output mode
+
+                                Ok(PioX::$port([<$port Generic>] { i: pin.i, _mode: PhantomData }))
+                            })*
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Pin mode selected at runtime
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum DynPinMode {
+    FloatingInput,
+    PullDownInput,
+    PullUpInput,
+    PushPullOutput,
+    OpenDrainOutput,
+    OpenDrainPullUpOutput,
+    PeripheralA,
+    PeripheralB,
+    PeripheralC,
+    PeripheralD,
+}
+
+/// Pin modes that can be converted to and from a DynPinMode
+pub trait PinMode {
+    const DYN: DynPinMode;
+}
+
+impl PinMode for Input<Floating> {
+    const DYN: DynPinMode = DynPinMode::FloatingInput;
+}
+
+impl PinMode for Input<PullDown> {
+    const DYN: DynPinMode = DynPinMode::PullDownInput;
+}
+
+impl PinMode for Input<PullUp> {
+    const DYN: DynPinMode = DynPinMode::PullUpInput;
+}
+
+impl PinMode for Output<PushPull> {
+    const DYN: DynPinMode = DynPinMode::PushPullOutput;
+}
+
+impl PinMode for Output<OpenDrain> {
+    const DYN: DynPinMode = DynPinMode::OpenDrainOutput;
+}
+
+impl PinMode for Output<OpenDrainPullUp> {
+    const DYN: DynPinMode = DynPinMode::OpenDrainPullUpOutput;
+}
+
+impl PinMode for PfA {
+    const DYN: DynPinMode = DynPinMode::PeripheralA;
+}
+
+impl PinMode for PfB {
+    const DYN: DynPinMode = DynPinMode::PeripheralB;
+}
+
+impl PinMode for PfC {
+    const DYN: DynPinMode = DynPinMode::PeripheralC;
+}
+
+impl PinMode for PfD {
+    const DYN: DynPinMode = DynPinMode::PeripheralD;
+}
+
+/// DynPin errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum DynPinError {
+    /// The operation is not available in the current pin mode
+    InvalidMode,
+}
+
+/// PIOB pins shared with a system function (CCFG_SYSIO)
+#[cfg(feature = "atsam4n")]
+const SYSIO_PINS: u32 = 0x10F0; // PB4-7, PB12
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+const SYSIO_PINS: u32 = 0x1CF0; // PB4-7, PB10-12
+
+/// A pin whose port, number and mode are only known at runtime
+///
+/// Converting a typed pin into a DynPin keeps its current configuration, converting it back
+/// with `try_into()` fails (returning the DynPin) if the port, number or mode do not match.
+///
+/// ```
+/// let mut bus: [DynPin; 2] = [pins.pa0.into(), pins.pb1.into_push_pull_output(&matrix).into()];
+/// for pin in bus.iter_mut() {
+///     pin.into_mode(DynPinMode::PullUpInput, &matrix);
+/// }
+/// let [pa0, pb1] = bus;
+/// let pa0: Pa0<Input<PullUp>> = pa0.try_into().unwrap();
+/// ```
+pub struct DynPin {
+    port: Port,
+    i: u8,
+    mode: DynPinMode,
+}
+
+impl DynPin {
+    pub fn port(&self) -> Port {
+        self.port
+    }
+
+    /// Pin number within the port
+    pub fn pin(&self) -> u8 {
+        self.i
+    }
+
+    pub fn mode(&self) -> DynPinMode {
+        self.mode
+    }
+
+    /// Reconfigures the pin
+    pub fn into_mode(&mut self, mode: DynPinMode, matrix: &MATRIX) {
+        if self.port == Port::PIOB && SYSIO_PINS & (1 << self.i) != 0 {
+            // Disable the system function to use the pin as PIO or peripheral
+            matrix
+                .ccfg_sysio
+                .modify(|r, w| unsafe { w.bits(r.bits() | (1 << self.i)) });
+        }
+        self.configure(mode);
+        self.mode = mode;
+    }
+
+    fn is_input(&self) -> bool {
+        matches!(
+            self.mode,
+            DynPinMode::FloatingInput | DynPinMode::PullDownInput | DynPinMode::PullUpInput
+        )
+    }
+
+    fn is_output(&self) -> bool {
+        matches!(
+            self.mode,
+            DynPinMode::PushPullOutput
+                | DynPinMode::OpenDrainOutput
+                | DynPinMode::OpenDrainPullUpOutput
+        )
+    }
+}
+
+impl InputPin for DynPin {
+    type Error = DynPinError;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        if !self.is_input() {
+            return Err(DynPinError::InvalidMode);
+        }
+        Ok(self.pin_data() & (1 << self.i) != 0)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+impl OutputPin for DynPin {
+    type Error = DynPinError;
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        if !self.is_output() {
+            return Err(DynPinError::InvalidMode);
+        }
+        self.set_output(true);
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        if !self.is_output() {
+            return Err(DynPinError::InvalidMode);
+        }
+        self.set_output(false);
+        Ok(())
+    }
+}
+
+impl StatefulOutputPin for DynPin {
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        if !self.is_output() {
+            return Err(DynPinError::InvalidMode);
+        }
+        Ok(self.output_data() & (1 << self.i) != 0)
+    }
+
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        self.is_set_high().map(|high| !high)
+    }
+}
+
+/// Software toggle (uses StatefulOutputPin and OutputPin)
+impl toggleable::Default for DynPin {}
+
+/// SPI master input pin
+pub trait MisoPin<SPI> {}
+/// SPI master output pin
+pub trait MosiPin<SPI> {}
+/// SPI clock pin
+pub trait SpckPin<SPI> {}
+/// SPI chip select `CS` pin
+pub trait NpcsPin<SPI, const CS: u8> {}
+/// UART/USART receive pin
+pub trait RxPin<UART> {}
+/// UART/USART transmit pin
+pub trait TxPin<UART> {}
+/// TWI data pin
+pub trait TwdPin<TWI> {}
+/// TWI clock pin
+pub trait TwckPin<TWI> {}
+/// PWM channel `CH` output (high side output on SAM4E/4S)
+pub trait PwmHPin<PWM, const CH: u8> {}
+/// PWM channel `CH` low side output
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+pub trait PwmLPin<PWM, const CH: u8> {}
+
+/// Implements the alternate function traits for each valid pin and peripheral function
+///
+/// Covers SPI, UART0/1, USART0/1, TWI0/1 and the PWM outputs. The SAM4N UART2, UART3, USART2
+/// and TWI2 are not mapped yet.
+macro_rules! alternate_functions {
+    ($($Trait:ident<$Periph:ident $(, $n:literal)?>: [$($Pin:ident<$Pf:ident>),+ $(,)?],)+) => {
+        $(
+            alternate_functions!(@impl [$Trait<crate::pac::$Periph $(, $n)?>] $($Pin<$Pf>),+);
+        )+
+    };
+    (@impl [$Trait:path] $($Pin:ident<$Pf:ident>),+) => {
+        $(
+            impl $Trait for $Pin<$Pf> {}
+        )+
+    };
+}
+
+alternate_functions!(
+    MisoPin<SPI>: [Pa12<PfA>],
+    MosiPin<SPI>: [Pa13<PfA>],
+    SpckPin<SPI>: [Pa14<PfA>],
+    NpcsPin<SPI, 0>: [Pa11<PfA>],
+    NpcsPin<SPI, 1>: [Pa9<PfB>, Pa31<PfA>, Pb14<PfA>],
+    NpcsPin<SPI, 2>: [Pa10<PfB>, Pa30<PfB>, Pb2<PfB>],
+    NpcsPin<SPI, 3>: [Pa3<PfB>, Pa5<PfB>, Pa22<PfB>],
+    RxPin<UART0>: [Pa9<PfA>],
+    TxPin<UART0>: [Pa10<PfA>],
+    RxPin<USART0>: [Pa5<PfA>],
+    TxPin<USART0>: [Pa6<PfA>],
+    RxPin<USART1>: [Pa21<PfA>],
+    TxPin<USART1>: [Pa22<PfA>],
+    TwdPin<TWI0>: [Pa3<PfA>],
+    TwckPin<TWI0>: [Pa4<PfA>],
+    TwdPin<TWI1>: [Pb4<PfA>],
+    TwckPin<TWI1>: [Pb5<PfA>],
+);
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4n"))]
+alternate_functions!(
+    RxPin<UART1>: [Pa5<PfC>],
+    TxPin<UART1>: [Pa6<PfC>],
+);
+
+#[cfg(feature = "atsam4s")]
+alternate_functions!(
+    RxPin<UART1>: [Pb2<PfA>],
+    TxPin<UART1>: [Pb3<PfA>],
+);
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+alternate_functions!(
+    PwmHPin<PWM, 0>: [Pa0<PfA>, Pa11<PfB>, Pa23<PfB>, Pb0<PfA>],
+    PwmHPin<PWM, 1>: [Pa1<PfA>, Pa12<PfB>, Pa24<PfB>, Pb1<PfA>],
+    PwmHPin<PWM, 2>: [Pa2<PfA>, Pa13<PfB>, Pa25<PfB>, Pb4<PfB>],
+    PwmHPin<PWM, 3>: [Pa7<PfB>, Pa14<PfB>, Pa17<PfC>, Pb14<PfB>],
+    PwmLPin<PWM, 0>: [Pa19<PfB>, Pb5<PfB>],
+    PwmLPin<PWM, 1>: [Pa20<PfB>, Pb12<PfA>],
+    PwmLPin<PWM, 2>: [Pa16<PfC>, Pa30<PfA>, Pb13<PfA>],
+    PwmLPin<PWM, 3>: [Pa15<PfC>],
+);
+
+#[cfg(any(feature = "atsam4s_c", feature = "atsam4e_e"))]
+alternate_functions!(
+    NpcsPin<SPI, 1>: [Pc4<PfB>],
+    PwmHPin<PWM, 0>: [Pc18<PfB>],
+    PwmHPin<PWM, 1>: [Pc19<PfB>],
+    PwmHPin<PWM, 2>: [Pc20<PfB>],
+    PwmHPin<PWM, 3>: [Pc21<PfB>],
+    PwmLPin<PWM, 0>: [Pc0<PfB>],
+    PwmLPin<PWM, 1>: [Pc1<PfB>],
+    PwmLPin<PWM, 2>: [Pc2<PfB>],
+    PwmLPin<PWM, 3>: [Pc3<PfB>, Pc22<PfB>],
+);
+
+#[cfg(feature = "atsam4e")]
+alternate_functions!(
+    PwmHPin<PWM, 0>: [Pd20<PfA>],
+    PwmHPin<PWM, 1>: [Pd21<PfA>],
+    PwmHPin<PWM, 2>: [Pd22<PfA>],
+    PwmHPin<PWM, 3>: [Pd23<PfA>],
+    PwmLPin<PWM, 0>: [Pd24<PfA>],
+    PwmLPin<PWM, 1>: [Pd25<PfA>],
+    PwmLPin<PWM, 2>: [Pd26<PfA>],
+    PwmLPin<PWM, 3>: [Pd27<PfA>],
+);
+
+#[cfg(feature = "atsam4n")]
+alternate_functions!(
+    PwmHPin<PWM, 0>: [Pa0<PfA>, Pa11<PfB>, Pa23<PfB>, Pb0<PfA>],
+    PwmHPin<PWM, 1>: [Pa1<PfA>, Pa12<PfB>, Pa24<PfB>, Pb1<PfA>],
+    PwmHPin<PWM, 2>: [Pa2<PfA>, Pa13<PfB>, Pa25<PfB>, Pb4<PfB>],
+    PwmHPin<PWM, 3>: [Pa7<PfB>, Pa14<PfB>, Pb14<PfB>],
+);
+
+/// Parallel data access to a PIO controller (PIO_OWER, PIO_OWDR, PIO_ODSR and PIO_PDSR)
+pub trait PioPort {
+    fn enable_output_write(mask: u32);
+    fn disable_output_write(mask: u32);
+    fn output_data() -> u32;
+    fn write_output_data(value: u32);
+    fn pin_data() -> u32;
+}
+
+/// A GPIO pin that can be part of a `PortBus`
+pub trait BusPin {
+    /// PIO controller the pin belongs to
+    type Port: PioPort;
+    /// Pin number within the port
+    const INDEX: u8;
+}
+
+/// Set of pins of a `PortBus`, the first pin is bit 0 of the bus value
+///
+/// Implemented for tuples of up to 16 pins of the same port, mixing pins of different ports
+/// does not compile.
+pub trait BusPins {
+    /// PIO controller the pins belong to
+    type Port: PioPort;
+    /// Pin numbers within the port, in bus bit order
+    const INDICES: &'static [u8];
+}
+
+macro_rules! bus_pins {
+    ($($P:ident),+) => {
+        impl<PORT: PioPort, $($P: BusPin<Port = PORT>),+> BusPins for ($($P,)+) {
+            type Port = PORT;
+            const INDICES: &'static [u8] = &[$($P::INDEX),+];
+        }
+    };
+}
+
+bus_pins!(P0);
+bus_pins!(P0, P1);
+bus_pins!(P0, P1, P2);
+bus_pins!(P0, P1, P2, P3);
+bus_pins!(P0, P1, P2, P3, P4);
+bus_pins!(P0, P1, P2, P3, P4, P5);
+bus_pins!(P0, P1, P2, P3, P4, P5, P6);
+bus_pins!(P0, P1, P2, P3, P4, P5, P6, P7);
+bus_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8);
+bus_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9);
+bus_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10);
+bus_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11);
+bus_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12);
+bus_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13);
+bus_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13, P14);
+bus_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13, P14, P15);
+
+/// Parallel bus made of several pins of the same PIO controller
+///
+/// All the pins of the bus are written at once through PIO_ODSR and read at once through
+/// PIO_PDSR. Output pins are driven, input pins can only be read.
+///
+/// ```
+/// let pins = (
+///     pins.pc0.into_push_pull_output(&matrix),
+///     pins.pc1.into_push_pull_output(&matrix),
+///     pins.pc2.into_push_pull_output(&matrix),
+///     pins.pc3.into_push_pull_output(&matrix),
+/// );
+/// let mut bus = PortBus::new(pins);
+/// bus.write(0b1010);
+/// ```
+pub struct PortBus<PINS: BusPins> {
+    pins: PINS,
+    mask: u32,
+    // Bus bit 0 position when the pins are contiguous and in order
+    shift: Option<u8>,
+}
+
+impl<PINS: BusPins> PortBus<PINS> {
+    pub fn new(pins: PINS) -> Self {
+        let mask = PINS::INDICES.iter().fold(0, |mask, i| mask | (1 << i));
+        let first = PINS::INDICES[0];
+        let contiguous = PINS::INDICES
+            .iter()
+            .enumerate()
+            .all(|(bit, &i)| i as usize == first as usize + bit);
+        PINS::Port::enable_output_write(mask); // Allow ODSR writes on the bus pins only
+
+        Self {
+            pins,
+            mask,
+            shift: contiguous.then_some(first),
+        }
+    }
+
+    /// Releases the pins
+    pub fn free(self) -> PINS {
+        PINS::Port::disable_output_write(self.mask);
+        self.pins
+    }
+
+    /// Port bit mask of the bus pins
+    pub fn mask(&self) -> u32 {
+        self.mask
+    }
+
+    /// Drives all the output pins of the bus at once, bit 0 of `value` is the first pin
+    ///
+    /// ODSR is shared with the other pins of the port that have output write enabled (e.g.
+    /// another `PortBus`), so it is updated in a critical section to keep their levels.
+    pub fn write(&mut self, value: u32) {
+        let bits = match self.shift {
+            Some(shift) => (value << shift) & self.mask,
+            None => PINS::INDICES
+                .iter()
+                .enumerate()
+                .filter(|(bit, _)| value & (1 << bit) != 0)
+                .fold(0, |bits, (_, i)| bits | (1 << i)),
+        };
+        // Other pins with output write enabled keep their current level
+        cortex_m::interrupt::free(|_| {
+            PINS::Port::write_output_data((PINS::Port::output_data() & !self.mask) | bits)
+        });
+    }
+
+    /// Reads the level of all the pins of the bus at once, bit 0 is the first pin
+    pub fn read(&self) -> u32 {
+        let data = PINS::Port::pin_data();
+        match self.shift {
+            Some(shift) => (data & self.mask) >> shift,
+            None => PINS::INDICES
+                .iter()
+                .enumerate()
+                .filter(|(_, &i)| data & (1 << i) != 0)
+                .fold(0, |value, (bit, _)| value | (1 << bit)),
+        }
+    }
+}
+
+/// Demultiplexes the input change interrupt of a PIO controller to its pins
+///
+/// Reading ISR clears the flags of all the pins of the port, so the PIOx interrupt handler must
+/// read it once and hand the changes to the owners of the individual pins.
+/// Pins without a registered handler are left pending and can still be polled with
+/// `is_interrupt_pending()`.
+///
+/// ```
+/// static BUTTONS: Mutex<RefCell<PortInterrupts>> =
+///     Mutex::new(RefCell::new(PortInterrupts::new(Port::PIOA)));
+///
+/// fn button_pressed() {
+///     // ...
+/// }
+///
+/// // Setup
+/// button.enable_interrupt(InterruptTrigger::FallingEdge);
+/// cortex_m::interrupt::free(|cs| BUTTONS.borrow(cs).borrow_mut().register(2, button_pressed));
+///
+/// #[interrupt]
+/// fn PIOA() {
+///     cortex_m::interrupt::free(|cs| BUTTONS.borrow(cs).borrow().dispatch());
+/// }
+/// ```
+pub struct PortInterrupts {
+    port: Port,
+    handlers: [Option<fn()>; 32],
+}
+
+impl PortInterrupts {
+    pub const fn new(port: Port) -> Self {
+        Self {
+            port,
+            handlers: [None; 32],
+        }
+    }
+
+    pub fn port(&self) -> Port {
+        self.port
+    }
+
+    /// Registers the handler called when `pin` (0-31) of the port has changed
+    pub fn register(&mut self, pin: u8, handler: fn()) {
+        self.handlers[pin as usize] = Some(handler);
+    }
+
+    /// Removes the handler of `pin` (0-31)
+    pub fn unregister(&mut self, pin: u8) {
+        self.handlers[pin as usize] = None;
+    }
+
+    /// Reads ISR once and returns the pins that changed and have their interrupt enabled
+    ///
+    /// The returned changes are cleared.
+    pub fn triggered(&self) -> TriggeredPins {
+        let mask = self.port.interrupt_mask();
+        TriggeredPins(self.port.pending_interrupts(mask) & mask)
+    }
+
+    /// Reads ISR once and calls the handlers of the pins that changed
+    pub fn dispatch(&self) {
+        let registered = self
+            .handlers
+            .iter()
+            .enumerate()
+            .filter(|(_, handler)| handler.is_some())
+            .fold(0, |mask, (pin, _)| mask | (1 << pin));
+        let mask = self.port.interrupt_mask() & registered;
+        for pin in TriggeredPins(self.port.pending_interrupts(mask) & mask) {
+            if let Some(handler) = self.handlers[pin as usize] {
+                handler();
+            }
+        }
+    }
+}
+
+/// Iterator over the indices of the pins that triggered an input change interrupt
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct TriggeredPins(u32);
+
+impl TriggeredPins {
+    /// Bit mask of the triggered pins
+    pub fn mask(&self) -> u32 {
+        self.0
+    }
+}
+
+impl Iterator for TriggeredPins {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.0 == 0 {
+            return None;
         }
+        let pin = self.0.trailing_zeros() as u8;
+        self.0 &= !(1 << pin);
+        Some(pin)
     }
 }
 
@@ -1604,3 +3165,322 @@ impl_pxx! {
     (PIOD),
     (PIOE)
 }
+
+/// Parallel capture data size, the captured bytes are packed into words of this type
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+pub trait CaptureWord: Copy {
+    /// PCMR DSIZE value
+    const DSIZE: u8;
+
+    /// Extracts the captured word from PCRHR
+    fn from_pcrhr(pcrhr: u32) -> Self;
+}
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+impl CaptureWord for u8 {
+    const DSIZE: u8 = 0;
+
+    fn from_pcrhr(pcrhr: u32) -> Self {
+        pcrhr as u8
+    }
+}
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+impl CaptureWord for u16 {
+    const DSIZE: u8 = 1;
+
+    fn from_pcrhr(pcrhr: u32) -> Self {
+        pcrhr as u16
+    }
+}
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+impl CaptureWord for u32 {
+    const DSIZE: u8 = 2;
+
+    fn from_pcrhr(pcrhr: u32) -> Self {
+        pcrhr
+    }
+}
+
+/// Parallel capture sampling
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum CaptureSampling {
+    /// Every data is sampled
+    All,
+    /// Only one data out of two is sampled, starting with the first one
+    Even,
+    /// Only one data out of two is sampled, starting with the second one
+    Odd,
+}
+
+/// Parallel capture errors
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum CaptureError {
+    /// Data was captured before the previous one was read
+    Overrun,
+}
+
+/// PIODC0 to PIODC7
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+pub type ParallelCaptureDataPins = (
+    Pa24<ExFn>,
+    Pa25<ExFn>,
+    Pa26<ExFn>,
+    Pa27<ExFn>,
+    Pa28<ExFn>,
+    Pa29<ExFn>,
+    Pa30<ExFn>,
+    Pa31<ExFn>,
+);
+
+/// Pins used by the parallel capture mode
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+pub struct ParallelCapturePins {
+    /// PIODCCLK
+    pub clock: Pa23<ExFn>,
+    /// PIODC0 to PIODC7
+    pub data: ParallelCaptureDataPins,
+    /// PIODCEN1 and PIODCEN2, data is sampled on every clock edge when not used
+    pub data_enable: Option<(Pa15<ExFn>, Pa16<ExFn>)>,
+}
+
+/// PIOA register block, the parallel capture registers are owned through the capture pins
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+struct PioaRegisters;
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+impl core::ops::Deref for PioaRegisters {
+    type Target = pioa::RegisterBlock;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*PIOA::ptr() }
+    }
+}
+
+/// PIOA parallel capture mode, samples the 8-bit PIODC bus on the rising edge of PIODCCLK
+///
+/// `WORD` selects how many samples are packed in each read (u8, u16 or u32).
+///
+/// ```
+/// let capture_pins = ParallelCapturePins {
+///     clock: pins.pa23.into_extra_function(&matrix),
+///     data: (
+///         pins.pa24.into_extra_function(&matrix),
+///         pins.pa25.into_extra_function(&matrix),
+///         pins.pa26.into_extra_function(&matrix),
+///         pins.pa27.into_extra_function(&matrix),
+///         pins.pa28.into_extra_function(&matrix),
+///         pins.pa29.into_extra_function(&matrix),
+///         pins.pa30.into_extra_function(&matrix),
+///         pins.pa31.into_extra_function(&matrix),
+///     ),
+///     data_enable: Some((
+///         pins.pa15.into_extra_function(&matrix),
+///         pins.pa16.into_extra_function(&matrix),
+///     )),
+/// };
+/// let capture = ParallelCapture::<u32>::new(capture_pins, CaptureSampling::All);
+///
+/// static mut FRAME: [u32; 320 * 240 / 4] = [0; 320 * 240 / 4];
+/// let transfer = capture.with_pdc().read(unsafe { &mut FRAME });
+/// let (frame, capture) = transfer.wait();
+/// ```
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+pub struct ParallelCapture<WORD: CaptureWord> {
+    pio: PioaRegisters,
+    pins: ParallelCapturePins,
+    _word: PhantomData<WORD>,
+}
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+impl<WORD: CaptureWord> ParallelCapture<WORD> {
+    pub fn new(pins: ParallelCapturePins, sampling: CaptureSampling) -> Self {
+        let pio = PioaRegisters;
+        let always_sample = pins.data_enable.is_none();
+        pio.pcmr.write(|w| unsafe {
+            w.pcen()
+                .clear_bit()
+                .dsize()
+                .bits(WORD::DSIZE)
+                .alwys()
+                .bit(always_sample)
+                .halfs()
+                .bit(sampling != CaptureSampling::All)
+                .frsts()
+                .bit(sampling == CaptureSampling::Odd)
+        });
+        unsafe {
+            pio.pcidr.write_with_zero(|w| w.bits(0xF)); // Disable all parallel capture interrupts
+        }
+
+        ParallelCapture {
+            pio,
+            pins,
+            _word: PhantomData,
+        }
+    }
+
+    /// Disables the parallel capture mode and releases the pins
+    pub fn free(mut self) -> ParallelCapturePins {
+        self.disable();
+        self.pins
+    }
+
+    /// Starts sampling the data bus
+    pub fn enable(&mut self) {
+        self.pio.pcmr.modify(|_, w| w.pcen().set_bit());
+    }
+
+    /// Stops sampling the data bus
+    pub fn disable(&mut self) {
+        self.pio.pcmr.modify(|_, w| w.pcen().clear_bit());
+    }
+
+    /// Reads the next captured word
+    pub fn read(&mut self) -> nb::Result<WORD, CaptureError> {
+        let pcisr = self.pio.pcisr.read(); // Clears OVRE
+        if pcisr.ovre().bit_is_set() {
+            Err(nb::Error::Other(CaptureError::Overrun))
+        } else if pcisr.drdy().bit_is_set() {
+            Ok(WORD::from_pcrhr(self.pio.pcrhr.read().bits()))
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Enable DRDY (Data Ready) interrupt
+    pub fn enable_data_ready_interrupt(&mut self) {
+        unsafe { self.pio.pcier.write_with_zero(|w| w.drdy().set_bit()) };
+    }
+
+    /// Disable DRDY (Data Ready) interrupt
+    pub fn disable_data_ready_interrupt(&mut self) {
+        unsafe { self.pio.pcidr.write_with_zero(|w| w.drdy().set_bit()) };
+    }
+
+    /// Enable OVRE (Overrun Error) interrupt
+    pub fn enable_overrun_interrupt(&mut self) {
+        unsafe { self.pio.pcier.write_with_zero(|w| w.ovre().set_bit()) };
+    }
+
+    /// Disable OVRE (Overrun Error) interrupt
+    pub fn disable_overrun_interrupt(&mut self) {
+        unsafe { self.pio.pcidr.write_with_zero(|w| w.ovre().set_bit()) };
+    }
+
+    /// Converts into a PDC receiver, the captured words are written to memory by the PDC
+    pub fn with_pdc(self) -> ParallelCaptureDma<WORD> {
+        RxDma {
+            payload: ParallelCapturePayload { capture: self },
+        }
+    }
+}
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+pdc_rx! { ParallelCapture<WORD: CaptureWord>: pio, pcisr, pcier, pcidr }
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+pub struct ParallelCapturePayload<WORD: CaptureWord> {
+    capture: ParallelCapture<WORD>,
+}
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+pub type ParallelCaptureDma<WORD> = RxDma<ParallelCapturePayload<WORD>>;
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+impl<WORD: CaptureWord> ParallelCaptureDma<WORD> {
+    /// Reverts the ParallelCaptureDma back to ParallelCapture
+    pub fn revert(mut self) -> ParallelCapture<WORD> {
+        // Disable capture and PDC in case they are still enabled
+        self.payload.capture.disable();
+        self.payload.capture.stop_rx_pdc();
+
+        self.payload.capture
+    }
+}
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+impl<WORD: CaptureWord> Receive for ParallelCaptureDma<WORD> {
+    type TransmittedWord = WORD;
+}
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+impl<B, WORD: CaptureWord> ReadDma<B, WORD> for ParallelCaptureDma<WORD>
+where
+    B: WriteBuffer<Word = WORD>,
+{
+    /// Assigns the buffer, enables PDC and starts sampling
+    fn read(mut self, mut buffer: B) -> Transfer<W, B, Self> {
+        // NOTE(unsafe) We own the buffer now and we won't call other `&mut` on it
+        // until the end of the transfer.
+        let (ptr, len) = unsafe { buffer.write_buffer() };
+        self.payload.capture.set_receive_address(ptr as u32);
+        self.payload.capture.set_receive_counter(len as u16);
+
+        compiler_fence(Ordering::Release);
+        self.start();
+
+        Transfer::w(buffer, self)
+    }
+}
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+impl<B, WORD: CaptureWord> ReadDmaPaused<B, WORD> for ParallelCaptureDma<WORD>
+where
+    B: WriteBuffer<Word = WORD>,
+{
+    /// Assigns the buffer, prepares PDC but does not start sampling
+    ///
+    /// transfer.resume() can be used to start the transfer
+    fn read_paused(mut self, mut buffer: B) -> Transfer<W, B, Self> {
+        // NOTE(unsafe) We own the buffer now and we won't call other `&mut` on it
+        // until the end of the transfer.
+        let (ptr, len) = unsafe { buffer.write_buffer() };
+        self.payload.capture.set_receive_address(ptr as u32);
+        self.payload.capture.set_receive_counter(len as u16);
+
+        compiler_fence(Ordering::Release);
+
+        Transfer::w(buffer, self)
+    }
+}
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4s"))]
+impl<WORD: CaptureWord> TransferPayload for ParallelCaptureDma<WORD> {
+    fn start(&mut self) {
+        self.payload.capture.start_rx_pdc();
+        self.payload.capture.enable(); // Start sampling
+    }
+    fn stop(&mut self) {
+        self.payload.capture.disable();
+        self.payload.capture.stop_rx_pdc();
+    }
+    fn in_progress(&self) -> bool {
+        self.payload.capture.rx_in_progress()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Compile-only checks, the pins are never constructed on the host
+
+    /// `into_output_pin()` on an input must infer the output type without annotations
+    #[allow(dead_code)]
+    fn into_output_pin_infers_push_pull(pin: Pa0<Input<Floating>>) {
+        let mut output = pin.into_output_pin(PinState::Low).unwrap();
+        output.set_high().unwrap();
+    }
+
+    #[allow(dead_code)]
+    fn open_drain_io_round_trip(pin: Pa0<Output<OpenDrain>>) {
+        let input = pin.into_input_pin().unwrap();
+        let _ = input.is_high();
+        let mut output = input.into_output_pin(PinState::High).unwrap();
+        output.set_low().unwrap();
+    }
+}
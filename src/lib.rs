@@ -83,6 +83,7 @@ pub mod delay;
 pub mod efc;
 pub mod gpio;
 pub mod pdc;
+pub mod power;
 pub mod prelude;
 pub mod rtt;
 pub mod serial;
@@ -203,7 +203,12 @@ macro_rules! pdc_rx {
     (
         $Periph:ident: $periph:ident, $isr:ident
     ) => {
-        impl $Periph {
+        $crate::pdc::pdc_rx! { $Periph<>: $periph, $isr, ier, idr }
+    };
+    (
+        $Periph:ident<$($G:ident: $Bound:path),*>: $periph:ident, $isr:ident, $ier:ident, $idr:ident
+    ) => {
+        impl<$($G: $Bound),*> $Periph<$($G),*> {
             /// Sets the PDC receive address pointer
             pub fn set_receive_address(&mut self, address: u32) {
                 self.$periph
@@ -256,23 +261,23 @@ macro_rules! pdc_rx {
             /// Enable ENDRX (End of Receive) interrupt
             /// Triggered when RCR reaches 0
             pub fn enable_endrx_interrupt(&mut self) {
-                unsafe { self.$periph.ier.write_with_zero(|w| w.endrx().set_bit()) };
+                unsafe { self.$periph.$ier.write_with_zero(|w| w.endrx().set_bit()) };
             }
 
             /// Disable ENDRX (End of Receive) interrupt
             pub fn disable_endrx_interrupt(&mut self) {
-                unsafe { self.$periph.idr.write_with_zero(|w| w.endrx().set_bit()) };
+                unsafe { self.$periph.$idr.write_with_zero(|w| w.endrx().set_bit()) };
             }
 
             /// Enable RXBUFF (Receive Buffer Full) interrupt
             /// Triggered when RCR and RNCR reach 0
             pub fn enable_rxbuff_interrupt(&mut self) {
-                unsafe { self.$periph.ier.write_with_zero(|w| w.rxbuff().set_bit()) };
+                unsafe { self.$periph.$ier.write_with_zero(|w| w.rxbuff().set_bit()) };
             }
 
             /// Disable RXBUFF (Receive Buffer Full) interrupt
             pub fn disable_rxbuff_interrupt(&mut self) {
-                unsafe { self.$periph.idr.write_with_zero(|w| w.rxbuff().set_bit()) };
+                unsafe { self.$periph.$idr.write_with_zero(|w| w.rxbuff().set_bit()) };
             }
         }
     };
@@ -283,7 +288,12 @@ macro_rules! pdc_tx {
     (
         $Periph:ident: $periph:ident, $isr:ident
     ) => {
-        impl $Periph {
+        $crate::pdc::pdc_tx! { $Periph<>: $periph, $isr, ier, idr }
+    };
+    (
+        $Periph:ident<$($G:ident: $Bound:path),*>: $periph:ident, $isr:ident, $ier:ident, $idr:ident
+    ) => {
+        impl<$($G: $Bound),*> $Periph<$($G),*> {
             /// Sets the PDC transmit address pointer
             pub fn set_transmit_address(&mut self, address: u32) {
                 self.$periph
@@ -341,14 +351,14 @@ macro_rules! pdc_tx {
             /// Triggered when RCR reaches 0
             pub fn enable_endtx_interrupt(&mut self) {
                 unsafe {
-                    self.$periph.ier.write_with_zero(|w| w.endtx().set_bit());
+                    self.$periph.$ier.write_with_zero(|w| w.endtx().set_bit());
                 }
             }
 
             /// Disable ENDRX (End of Transmit) interrupt
             pub fn disable_endtx_interrupt(&mut self) {
                 unsafe {
-                    self.$periph.idr.write_with_zero(|w| w.endtx().set_bit());
+                    self.$periph.$idr.write_with_zero(|w| w.endtx().set_bit());
                 }
             }
 
@@ -356,14 +366,14 @@ macro_rules! pdc_tx {
             /// Triggered when RCR and RNCR reach 0
             pub fn enable_txbufe_interrupt(&mut self) {
                 unsafe {
-                    self.$periph.ier.write_with_zero(|w| w.txbufe().set_bit());
+                    self.$periph.$ier.write_with_zero(|w| w.txbufe().set_bit());
                 }
             }
 
             /// Disable RXBUFF (Transmit Buffer Full) interrupt
             pub fn disable_txbufe_interrupt(&mut self) {
                 unsafe {
-                    self.$periph.idr.write_with_zero(|w| w.txbufe().set_bit());
+                    self.$periph.$idr.write_with_zero(|w| w.txbufe().set_bit());
                 }
             }
         }
@@ -375,7 +385,12 @@ macro_rules! pdc_rxtx {
     (
         $Periph:ident: $periph:ident
     ) => {
-        impl $Periph {
+        $crate::pdc::pdc_rxtx! { $Periph<>: $periph }
+    };
+    (
+        $Periph:ident<$($G:ident: $Bound:path),*>: $periph:ident
+    ) => {
+        impl<$($G: $Bound),*> $Periph<$($G),*> {
             /// Starts the PDC transfer (rx+tx)
             pub fn start_rxtx_pdc(&mut self) {
                 unsafe {
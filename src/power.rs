@@ -0,0 +1,210 @@
+//! Low-power modes
+//!
+//! - Sleep: the core clock is stopped until an interrupt occurs, peripherals keep running
+//! - Wait: all clocks are stopped until a fast startup event occurs, RAM and registers are kept
+//! - Backup: only the backup area (SUPC, RTT, RTC, backup registers) is powered, the device
+//!   resets when a wake-up event occurs
+
+use crate::clock::{
+    ClockConfig, ClockController, ClockError, Clocks, MainClock, MasterClockSource,
+};
+use crate::pac::SUPC;
+use cortex_m::peripheral::SCB;
+
+#[cfg(any(feature = "atsam4e", feature = "atsam4n"))]
+use crate::pac::EFC;
+
+#[cfg(feature = "atsam4s")]
+use crate::pac::EFC0;
+
+#[cfg(feature = "atsam4sd")]
+use crate::pac::EFC1;
+
+/// Fast startup sources used to exit Wait mode (PMC_FSMR and PMC_FSPR)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub struct FastStartup {
+    /// WKUP0 to WKUP15 inputs enabled as fast startup sources (bit mask)
+    pub wakeup_inputs: u16,
+    /// Active high WKUP inputs (bit mask), others are active low
+    pub wakeup_inputs_active_high: u16,
+    /// RTT alarm
+    pub rtt_alarm: bool,
+    /// RTC alarm
+    pub rtc_alarm: bool,
+    /// USB resume
+    #[cfg(not(feature = "atsam4n"))]
+    pub usb: bool,
+}
+
+impl FastStartup {
+    /// PMC_FSMR value
+    fn fsmr(&self) -> u32 {
+        let mut fsmr = self.wakeup_inputs as u32; // FSTT0-15
+        if self.rtt_alarm {
+            fsmr |= 1 << 16; // RTTAL
+        }
+        if self.rtc_alarm {
+            fsmr |= 1 << 17; // RTCAL
+        }
+        #[cfg(not(feature = "atsam4n"))]
+        if self.usb {
+            fsmr |= 1 << 18; // USBAL
+        }
+        fsmr
+    }
+}
+
+/// Wake-up sources used to exit Backup mode (SUPC_WUMR and SUPC_WUIR)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub struct BackupWakeup {
+    /// WKUP0 to WKUP15 inputs enabled as wake-up sources (bit mask)
+    pub wakeup_inputs: u16,
+    /// Active high WKUP inputs (bit mask), others are active low
+    pub wakeup_inputs_active_high: u16,
+    /// RTT alarm
+    pub rtt_alarm: bool,
+    /// RTC alarm
+    pub rtc_alarm: bool,
+}
+
+/// Low-power mode manager
+///
+/// ```
+/// let mut power = PowerManager::new(peripherals.SUPC);
+///
+/// // Sleep until the next interrupt
+/// power.sleep(&mut core.SCB);
+///
+/// // Wait until WKUP0 goes low or the RTT alarm fires, the clocks are restored afterwards
+/// let fast_startup = FastStartup {
+///     wakeup_inputs: 1 << 0,
+///     rtt_alarm: true,
+///     ..Default::default()
+/// };
+/// let clocks = power
+///     .wait(&mut clock_controller, &peripherals.EFC, fast_startup)
+///     .unwrap();
+/// uart.clocks_changed(clocks);
+/// ```
+pub struct PowerManager {
+    supc: SUPC,
+}
+
+impl PowerManager {
+    pub fn new(supc: SUPC) -> Self {
+        Self { supc }
+    }
+
+    /// Releases the SUPC peripheral
+    pub fn free(self) -> SUPC {
+        self.supc
+    }
+
+    /// Enters Sleep mode until an interrupt occurs
+    pub fn sleep(&mut self, scb: &mut SCB) {
+        scb.clear_sleepdeep();
+        cortex_m::asm::dsb();
+        cortex_m::asm::wfi();
+    }
+
+    /// Enters Wait mode until one of the fast startup sources is triggered
+    ///
+    /// The master clock is switched to the fast RC oscillator and the main crystal oscillator is
+    /// stopped before entering Wait mode. The previous clock configuration (oscillator, PLLs,
+    /// USB clock and flash wait states) is restored on wake-up.
+    /// Returns the restored clock frequencies.
+    pub fn wait(
+        &mut self,
+        clocks: &mut ClockController,
+        #[cfg(any(feature = "atsam4e", feature = "atsam4n"))] efc: &EFC,
+        #[cfg(feature = "atsam4s")] efc0: &EFC0,
+        #[cfg(feature = "atsam4sd")] efc1: &EFC1,
+        fast_startup: FastStartup,
+    ) -> Result<Clocks, ClockError> {
+        let config = clocks.config();
+
+        // Wait mode must be entered while running from the fast RC oscillator
+        // with 0 flash wait states
+        #[cfg(not(feature = "atsam4n"))]
+        let main_clock = MainClock::RcOscillator4Mhz;
+        #[cfg(feature = "atsam4n")]
+        let main_clock = MainClock::RcOscillator8Mhz;
+        let rc_config = ClockConfig::new(main_clock, main_clock.frequency(), false)
+            .with_source(MasterClockSource::MainClock)
+            .with_core_voltage(config.core_voltage);
+        clocks.reconfigure(
+            #[cfg(any(feature = "atsam4e", feature = "atsam4n"))]
+            efc,
+            #[cfg(feature = "atsam4s")]
+            efc0,
+            #[cfg(feature = "atsam4sd")]
+            efc1,
+            rc_config,
+        )?;
+
+        let pmc = &clocks.pmc;
+
+        // Stop the main crystal oscillator, it is restarted (waiting for MOSCXTS) when the
+        // previous configuration is restored below
+        pmc.ckgr_mor
+            .modify(|_, w| w.key().passwd().moscxten().clear_bit());
+
+        unsafe {
+            pmc.pmc_fspr
+                .write_with_zero(|w| w.bits(fast_startup.wakeup_inputs_active_high as u32)); // Fast startup polarity
+            pmc.pmc_fsmr.write_with_zero(|w| {
+                w.bits(fast_startup.fsmr()) // Fast startup sources
+                    .flpm()
+                    .flash_standby()
+            });
+        }
+
+        // Enter Wait mode
+        pmc.ckgr_mor
+            .modify(|_, w| w.key().passwd().waitmode().set_bit());
+        while pmc.pmc_sr.read().mckrdy().bit_is_clear() {}
+
+        // Make sure the core does not execute instructions before the fast RC is running again
+        cortex_m::asm::delay(500);
+        while pmc.pmc_sr.read().moscrcs().bit_is_clear() {}
+
+        clocks.reconfigure(
+            #[cfg(any(feature = "atsam4e", feature = "atsam4n"))]
+            efc,
+            #[cfg(feature = "atsam4s")]
+            efc0,
+            #[cfg(feature = "atsam4sd")]
+            efc1,
+            config,
+        )
+    }
+
+    /// Enters Backup mode, the device resets when one of the wake-up sources is triggered
+    pub fn backup(&mut self, scb: &mut SCB, wakeup: BackupWakeup) -> ! {
+        unsafe {
+            self.supc.wuir.write_with_zero(|w| {
+                w.bits(
+                    wakeup.wakeup_inputs as u32 | (wakeup.wakeup_inputs_active_high as u32) << 16,
+                )
+            }); // WKUPEN0-15 and WKUPT0-15
+        }
+        self.supc.wumr.modify(|_, w| {
+            w.rtten()
+                .bit(wakeup.rtt_alarm)
+                .rtcen()
+                .bit(wakeup.rtc_alarm)
+        });
+
+        scb.set_sleepdeep();
+        unsafe {
+            self.supc
+                .cr
+                .write_with_zero(|w| w.key().passwd().vroff().stop_vreg());
+        }
+
+        loop {
+            cortex_m::asm::dsb();
+            cortex_m::asm::wfi();
+        }
+    }
+}
@@ -2,10 +2,13 @@ extern crate nb;
 
 // device crate
 use {
-    crate::clock::{Enabled, Uart0Clock, Uart1Clock},
-    crate::gpio::{Pa10, Pa9, PfA},
+    crate::clock::{ClockChangeListener, Clocks, Enabled, Uart0Clock, Uart1Clock},
+    crate::gpio::{Pa10, Pa9, PfA, RxPin, TxPin},
     crate::pac::{UART0, UART1},
-    core::marker::PhantomData,
+    crate::pdc::*,
+    core::sync::atomic::{compiler_fence, Ordering},
+    embedded_dma::{ReadBuffer, WriteBuffer},
+    fugit::HertzU32 as Hertz,
     hal::{serial::Read, serial::Write},
     paste::paste,
 };
@@ -39,11 +42,85 @@ pub enum StopBits {
     Two,
 }
 
-#[derive(Debug, defmt::Format)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
 pub enum Error {
     /// Buffer overrun
     Overrun,
-    // omitted: other error variants
+    /// Stop bit not detected
+    Framing,
+    /// Parity bit mismatch
+    Parity,
+}
+
+/// Ring buffer over a caller-provided static buffer
+struct RingBuffer {
+    buffer: &'static mut [u8],
+    start: usize,
+    len: usize,
+}
+
+impl RingBuffer {
+    fn new(buffer: &'static mut [u8]) -> Self {
+        RingBuffer {
+            buffer,
+            start: 0,
+            len: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn push(&mut self, byte: u8) -> bool {
+        if self.len == self.buffer.len() {
+            return false;
+        }
+        let end = (self.start + self.len) % self.buffer.len();
+        self.buffer[end] = byte;
+        self.len += 1;
+        true
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.buffer[self.start];
+        self.start = (self.start + 1) % self.buffer.len();
+        self.len -= 1;
+        Some(byte)
+    }
+}
+
+/// Interrupt-driven UART, received and transmitted bytes go through static ring buffers
+///
+/// `on_interrupt()` must be called from the UART interrupt handler, with the UART interrupt
+/// unmasked in the NVIC.
+///
+/// ```
+/// static mut RX_BUFFER: [u8; 256] = [0; 256];
+/// static mut TX_BUFFER: [u8; 256] = [0; 256];
+///
+/// let mut serial = Serial0::new(/* ... */)
+///     .into_buffered(unsafe { &mut RX_BUFFER }, unsafe { &mut TX_BUFFER });
+///
+/// // RTIC task bound to the UART0 interrupt
+/// #[task(binds = UART0, resources = [serial])]
+/// fn uart0(cx: uart0::Context) {
+///     cx.resources.serial.on_interrupt();
+/// }
+///
+/// // Elsewhere, with the serial resource locked
+/// let written = serial.write(b"hello");
+/// let mut data = [0; 16];
+/// let received = serial.read(&mut data)?;
+/// ```
+pub struct BufferedUart<UART> {
+    uart: UART,
+    rx: RingBuffer,
+    tx: RingBuffer,
+    error: Option<Error>,
 }
 
 macro_rules! uarts {
@@ -58,31 +135,29 @@ macro_rules! uarts {
     ) => {
         paste! {
             $(
-                pub struct $PortType {
+                /// RX and TX can be any pin implementing `RxPin` and `TxPin` for the UART
+                pub struct $PortType<RX = $pin_rx, TX = $pin_tx> {
                     uart: $UART,
-                    clock: PhantomData<[<$Uart Clock>]<Enabled>>,
-                    rx_pin: PhantomData<$pin_rx>,
-                    tx_pin: PhantomData<$pin_tx>,
+                    baud_rate: u32,
+                    clock: [<$Uart Clock>]<Enabled>,
+                    rx_pin: RX,
+                    tx_pin: TX,
                 }
 
-                impl $PortType {
+                impl<RX: RxPin<$UART>, TX: TxPin<$UART>> $PortType<RX, TX> {
                     pub fn new (
                         mut uart: $UART,
                         clock: [<$Uart Clock>]<Enabled>,
-                        _rx_pin: $pin_rx,
-                        _tx_pin: $pin_tx,
+                        clocks: Clocks,
+                        rx_pin: RX,
+                        tx_pin: TX,
                         baud_rate: u32,
                         parity: Option<Parity>,
                     ) -> Self {
                         Self::reset_and_disable(&mut uart);
 
-                        let clock_divisor:u32 = ((clock.frequency() / baud_rate) / 16).raw();
-                        if !(1..=65535).contains(&clock_divisor) {
-                            panic!("Unsupported baud_rate specified for serial device (cd = {})", clock_divisor);
-                        }
-
                         // Configure the baud rate generator
-                        uart.brgr.write(|w| unsafe { w.bits(clock_divisor) });
+                        Self::set_baud_rate(&mut uart, clocks.master_clock(), baud_rate);
 
                         // Configure the mode
                         uart.mr.write(|w| unsafe {
@@ -107,12 +182,28 @@ macro_rules! uarts {
 
                         $PortType {
                             uart,
-                            clock: PhantomData,
-                            rx_pin: PhantomData,
-                            tx_pin: PhantomData,
+                            baud_rate,
+                            clock,
+                            rx_pin,
+                            tx_pin,
                         }
                     }
 
+                    /// Disables the UART and releases the peripheral, clock and pins
+                    pub fn free(mut self) -> ($UART, [<$Uart Clock>]<Enabled>, RX, TX) {
+                        Self::reset_and_disable(&mut self.uart);
+                        (self.uart, self.clock, self.rx_pin, self.tx_pin)
+                    }
+
+                    fn set_baud_rate(uart: &mut $UART, master_clock: Hertz, baud_rate: u32) {
+                        let clock_divisor:u32 = ((master_clock / baud_rate) / 16).raw();
+                        if !(1..=65535).contains(&clock_divisor) {
+                            panic!("Unsupported baud_rate specified for serial device (cd = {})", clock_divisor);
+                        }
+
+                        uart.brgr.write(|w| unsafe { w.bits(clock_divisor) });
+                    }
+
                     fn reset_and_disable(uart: &mut $UART) {
                         unsafe { uart.cr.write_with_zero(|w| {
                             w.rstrx().set_bit().rsttx().set_bit().rxdis().set_bit().txdis().set_bit()
@@ -125,6 +216,11 @@ macro_rules! uarts {
                         })};
                     }
 
+                    /// Clears the overrun, framing and parity error flags
+                    fn reset_status(&mut self) {
+                        unsafe { self.uart.cr.write_with_zero(|w| w.rststa().set_bit()) };
+                    }
+
                     pub fn write_string_blocking(&mut self, data: &str) {
                         for c in data.chars() {
                             loop {
@@ -138,19 +234,260 @@ macro_rules! uarts {
                     }
                 }
 
-                impl Read<u8> for $PortType {
+                impl<RX: RxPin<$UART>, TX: TxPin<$UART>> $PortType<RX, TX> {
+                    /// Switches to interrupt-driven operation using the given ring buffers
+                    ///
+                    /// The buffers must not be empty.
+                    pub fn into_buffered(
+                        self,
+                        rx_buffer: &'static mut [u8],
+                        tx_buffer: &'static mut [u8],
+                    ) -> BufferedUart<Self> {
+                        assert!(!rx_buffer.is_empty() && !tx_buffer.is_empty());
+                        unsafe { self.uart.ier.write_with_zero(|w| w.rxrdy().set_bit()) }; // Enable the receive interrupt
+                        BufferedUart {
+                            uart: self,
+                            rx: RingBuffer::new(rx_buffer),
+                            tx: RingBuffer::new(tx_buffer),
+                            error: None,
+                        }
+                    }
+                }
+
+                impl<RX: RxPin<$UART>, TX: TxPin<$UART>> BufferedUart<$PortType<RX, TX>> {
+                    /// Moves received bytes into the receive buffer and feeds the transmitter
+                    ///
+                    /// Must be called from the UART interrupt handler.
+                    pub fn on_interrupt(&mut self) {
+                        let uart = &mut self.uart.uart;
+                        let sr = uart.sr.read();
+
+                        if sr.ovre().bit_is_set() {
+                            self.error = Some(Error::Overrun);
+                        } else if sr.frame().bit_is_set() {
+                            self.error = Some(Error::Framing);
+                        } else if sr.pare().bit_is_set() {
+                            self.error = Some(Error::Parity);
+                        }
+                        if sr.ovre().bit_is_set() || sr.frame().bit_is_set() || sr.pare().bit_is_set() {
+                            unsafe { uart.cr.write_with_zero(|w| w.rststa().set_bit()) }; // Clear the error flags
+                        }
+
+                        if sr.rxrdy().bit_is_set() {
+                            let byte = uart.rhr.read().bits() as u8;
+                            if !self.rx.push(byte) {
+                                // Receive buffer full, the byte is dropped
+                                self.error = Some(Error::Overrun);
+                            }
+                        }
+
+                        if sr.txrdy().bit_is_set() && uart.imr.read().txrdy().bit_is_set() {
+                            match self.tx.pop() {
+                                Some(byte) => unsafe { uart.thr.write_with_zero(|w| w.txchr().bits(byte)) },
+                                None => unsafe { uart.idr.write_with_zero(|w| w.txrdy().set_bit()) }, // Nothing left to send
+                            }
+                        }
+                    }
+
+                    /// Copies received bytes into `data`, returning the number of bytes copied
+                    ///
+                    /// An error is returned once for each error detected since the previous
+                    /// call, bytes received in the meantime are kept in the buffer.
+                    pub fn read(&mut self, data: &mut [u8]) -> Result<usize, Error> {
+                        if let Some(error) = self.error.take() {
+                            return Err(error);
+                        }
+
+                        let mut count = 0;
+                        for slot in data.iter_mut() {
+                            match self.rx.pop() {
+                                Some(byte) => *slot = byte,
+                                None => break,
+                            }
+                            count += 1;
+                        }
+                        Ok(count)
+                    }
+
+                    /// Queues as many bytes of `data` as fit in the transmit buffer, returning
+                    /// the number of bytes queued
+                    pub fn write(&mut self, data: &[u8]) -> usize {
+                        let count = data.iter().take_while(|&&byte| self.tx.push(byte)).count();
+                        if count > 0 {
+                            unsafe { self.uart.uart.ier.write_with_zero(|w| w.txrdy().set_bit()) }; // Enable the transmit interrupt
+                        }
+                        count
+                    }
+
+                    /// Completes once the transmit buffer is drained and the last character sent
+                    pub fn flush(&mut self) -> nb::Result<(), Error> {
+                        if self.tx.is_empty() && self.uart.uart.sr.read().txempty().bit_is_set() {
+                            Ok(())
+                        } else {
+                            Err(nb::Error::WouldBlock)
+                        }
+                    }
+
+                    /// Disables the UART interrupts and releases the UART and buffers
+                    pub fn free(self) -> ($PortType<RX, TX>, &'static mut [u8], &'static mut [u8]) {
+                        unsafe {
+                            self.uart.uart.idr.write_with_zero(|w| w.rxrdy().set_bit().txrdy().set_bit())
+                        };
+                        (self.uart, self.rx.buffer, self.tx.buffer)
+                    }
+                }
+
+                impl<RX: RxPin<$UART>, TX: TxPin<$UART>> $PortType<RX, TX> {
+                    /// UART with PDC, Rx only
+                    ///
+                    /// ```
+                    /// static mut PACKET: [u8; 64] = [0; 64];
+                    ///
+                    /// let transfer = serial.with_pdc_rx().read(unsafe { &mut PACKET });
+                    /// let (packet, rx) = transfer.wait();
+                    /// let serial = rx.revert();
+                    /// ```
+                    pub fn with_pdc_rx(self) -> [<$PortType RxDma>]<RX, TX> {
+                        RxDma {
+                            payload: [<$PortType Payload>] { uart: self },
+                        }
+                    }
+
+                    /// UART with PDC, Tx only
+                    pub fn with_pdc_tx(self) -> [<$PortType TxDma>]<RX, TX> {
+                        TxDma {
+                            payload: [<$PortType Payload>] { uart: self },
+                        }
+                    }
+                }
+
+                pdc_rx! { $PortType<RX: RxPin<$UART>, TX: TxPin<$UART>>: uart, sr, ier, idr }
+                pdc_tx! { $PortType<RX: RxPin<$UART>, TX: TxPin<$UART>>: uart, sr, ier, idr }
+
+                pub struct [<$PortType Payload>]<RX = $pin_rx, TX = $pin_tx> {
+                    uart: $PortType<RX, TX>,
+                }
+
+                pub type [<$PortType RxDma>]<RX = $pin_rx, TX = $pin_tx> = RxDma<[<$PortType Payload>]<RX, TX>>;
+                pub type [<$PortType TxDma>]<RX = $pin_rx, TX = $pin_tx> = TxDma<[<$PortType Payload>]<RX, TX>>;
+
+                impl<RX: RxPin<$UART>, TX: TxPin<$UART>> Receive for [<$PortType RxDma>]<RX, TX> {
+                    type TransmittedWord = u8;
+                }
+
+                impl<RX: RxPin<$UART>, TX: TxPin<$UART>> Transmit for [<$PortType TxDma>]<RX, TX> {
+                    type ReceivedWord = u8;
+                }
+
+                impl<RX: RxPin<$UART>, TX: TxPin<$UART>> [<$PortType RxDma>]<RX, TX> {
+                    /// Reverts the RxDma back to the UART
+                    pub fn revert(mut self) -> $PortType<RX, TX> {
+                        self.payload.uart.stop_rx_pdc();
+                        self.payload.uart
+                    }
+                }
+
+                impl<B, RX: RxPin<$UART>, TX: TxPin<$UART>> ReadDma<B, u8> for [<$PortType RxDma>]<RX, TX>
+                where
+                    Self: TransferPayload,
+                    B: WriteBuffer<Word = u8>,
+                {
+                    /// Assigns the buffer, enables PDC and starts receiving
+                    fn read(mut self, mut buffer: B) -> Transfer<W, B, Self> {
+                        // NOTE(unsafe) We own the buffer now and we won't call other `&mut` on it
+                        // until the end of the transfer.
+                        let (ptr, len) = unsafe { buffer.write_buffer() };
+                        self.payload.uart.set_receive_address(ptr as u32);
+                        self.payload.uart.set_receive_counter(len as u16);
+
+                        compiler_fence(Ordering::Release);
+                        self.start();
+
+                        Transfer::w(buffer, self)
+                    }
+                }
+
+                impl<RX: RxPin<$UART>, TX: TxPin<$UART>> TransferPayload for [<$PortType RxDma>]<RX, TX> {
+                    fn start(&mut self) {
+                        self.payload.uart.start_rx_pdc();
+                    }
+                    fn stop(&mut self) {
+                        self.payload.uart.stop_rx_pdc();
+                    }
+                    fn in_progress(&self) -> bool {
+                        self.payload.uart.rx_in_progress()
+                    }
+                }
+
+                impl<RX: RxPin<$UART>, TX: TxPin<$UART>> [<$PortType TxDma>]<RX, TX> {
+                    /// Reverts the TxDma back to the UART
+                    pub fn revert(mut self) -> $PortType<RX, TX> {
+                        self.payload.uart.stop_tx_pdc();
+                        self.payload.uart
+                    }
+                }
+
+                impl<B, RX: RxPin<$UART>, TX: TxPin<$UART>> WriteDma<B, u8> for [<$PortType TxDma>]<RX, TX>
+                where
+                    Self: TransferPayload,
+                    B: ReadBuffer<Word = u8>,
+                {
+                    /// Assigns the write buffer, enables PDC and starts transmitting
+                    fn write(mut self, buffer: B) -> Transfer<R, B, Self> {
+                        // NOTE(unsafe) We own the buffer now and we won't call other `&mut` on it
+                        // until the end of the transfer.
+                        let (ptr, len) = unsafe { buffer.read_buffer() };
+                        self.payload.uart.set_transmit_address(ptr as u32);
+                        self.payload.uart.set_transmit_counter(len as u16);
+
+                        compiler_fence(Ordering::Release);
+                        self.start();
+
+                        Transfer::r(buffer, self)
+                    }
+                }
+
+                impl<RX: RxPin<$UART>, TX: TxPin<$UART>> TransferPayload for [<$PortType TxDma>]<RX, TX> {
+                    fn start(&mut self) {
+                        self.payload.uart.start_tx_pdc();
+                    }
+                    fn stop(&mut self) {
+                        self.payload.uart.stop_tx_pdc();
+                    }
+                    fn in_progress(&self) -> bool {
+                        self.payload.uart.tx_in_progress()
+                    }
+                }
+
+                impl<RX: RxPin<$UART>, TX: TxPin<$UART>> ClockChangeListener for $PortType<RX, TX> {
+                    /// Recomputes the baud rate divisor, any character in progress may be corrupted
+                    fn clocks_changed(&mut self, clocks: Clocks) {
+                        Self::set_baud_rate(&mut self.uart, clocks.master_clock(), self.baud_rate);
+                    }
+                }
+
+                impl<RX: RxPin<$UART>, TX: TxPin<$UART>> Read<u8> for $PortType<RX, TX> {
                     type Error = Error;
 
                     fn read(&mut self) -> nb::Result<u8, Error> {
                         // read the status register
                         let isr = self.uart.sr.read();
 
-                        if isr.ovre().bit_is_set() {
-                            // Error: Buffer overrun
-                            Err(nb::Error::Other(Error::Overrun))
-                        }
-                        // omitted: checks for other errors
-                        else if isr.rxrdy().bit_is_set() {
+                        let error = if isr.ovre().bit_is_set() {
+                            Some(Error::Overrun)
+                        } else if isr.frame().bit_is_set() {
+                            Some(Error::Framing)
+                        } else if isr.pare().bit_is_set() {
+                            Some(Error::Parity)
+                        } else {
+                            None
+                        };
+
+                        if let Some(error) = error {
+                            // The received character is kept in RHR and can still be read
+                            self.reset_status();
+                            Err(nb::Error::Other(error))
+                        } else if isr.rxrdy().bit_is_set() {
                             // Data available: read the data register
                             Ok(self.uart.rhr.read().bits() as u8)
                         } else {
@@ -160,25 +497,28 @@ macro_rules! uarts {
                     }
                 }
 
-                impl Write<u8> for $PortType {
+                impl<RX: RxPin<$UART>, TX: TxPin<$UART>> Write<u8> for $PortType<RX, TX> {
                     type Error = Error;
 
                     fn write(&mut self, byte: u8) -> nb::Result<(), Error> {
                         // read the status register
                         let isr = self.uart.sr.read();
 
-                        // omitted: checks for other errors
                         if isr.txrdy().bit_is_set() {
                             unsafe { Ok(self.uart.thr.write_with_zero(|w| w.txchr().bits(byte) )) }
                         } else {
-                            // No data available yet
+                            // Transmit holding register still full
                             Err(nb::Error::WouldBlock)
                         }
                     }
 
                     fn flush(&mut self) -> nb::Result<(), Error> {
-                        // No data available yet
-                        Err(nb::Error::WouldBlock)
+                        // Complete once the holding and shift registers are both empty
+                        if self.uart.sr.read().txempty().bit_is_set() {
+                            Ok(())
+                        } else {
+                            Err(nb::Error::WouldBlock)
+                        }
                     }
                 }
             )+
@@ -198,5 +538,8 @@ uarts!(
     Uart1: (UART1, Uart1, uart1, Pb2<PfA>, Pb3<PfA>),
 );
 
-pub type Serial0 = Uart0;
-pub type Serial1 = Uart1;
+pub type Serial0<RX = Pa9<PfA>, TX = Pa10<PfA>> = Uart0<RX, TX>;
+#[cfg(any(feature = "atsam4e", feature = "atsam4n"))]
+pub type Serial1<RX = Pa5<PfC>, TX = Pa6<PfC>> = Uart1<RX, TX>;
+#[cfg(feature = "atsam4s")]
+pub type Serial1<RX = Pb2<PfA>, TX = Pb3<PfA>> = Uart1<RX, TX>;
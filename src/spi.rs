@@ -1,6 +1,6 @@
 //! SPI Implementation
-use crate::clock::{get_master_clock_frequency, Enabled, SpiClock};
-use crate::gpio::{Pa12, Pa13, Pa14, PfA};
+use crate::clock::{ClockChangeListener, Clocks, Enabled, SpiClock};
+use crate::gpio::{MisoPin, MosiPin, Pa12, Pa13, Pa14, PfA, SpckPin};
 use crate::pac::SPI;
 use crate::pdc::*;
 use core::marker::PhantomData;
@@ -147,7 +147,7 @@ pub enum PeripheralSelectMode {
 pub struct ChipSelectSettings {
     mode: spi::Mode,
     csa: ChipSelectActive,
-    scbr: u8,
+    baud: Hertz,
     dlybs: u8,
     dlybct: u8,
     bits: BitWidth,
@@ -172,19 +172,10 @@ impl ChipSelectSettings {
         dlybs: u8,
         dlybct: u8,
     ) -> ChipSelectSettings {
-        let pclk = get_master_clock_frequency();
-
-        // Calculate baud divider
-        // (f_periph + baud - 1) / baud
-        let scbr = ((pclk.raw() + baud.raw() - 1) / baud.raw()) as u8;
-        if scbr < 1 {
-            panic!("scbr must be greater than 0: {}", scbr);
-        }
-
         ChipSelectSettings {
             mode,
             csa,
-            scbr,
+            baud,
             dlybs,
             dlybct,
             bits,
@@ -192,6 +183,16 @@ impl ChipSelectSettings {
     }
 }
 
+/// Calculate baud divider
+/// (f_periph + baud - 1) / baud
+fn scbr(pclk: Hertz, baud: Hertz) -> u8 {
+    let scbr = ((pclk.raw() + baud.raw() - 1) / baud.raw()) as u8;
+    if scbr < 1 {
+        panic!("scbr must be greater than 0: {}", scbr);
+    }
+    scbr
+}
+
 /// SPI Master
 ///
 /// Example on how to individually read/write to SPI CS channels
@@ -229,6 +230,7 @@ impl ChipSelectSettings {
 /// let mut spi = SpiMaster::<SpiU8>::new(
 ///     cx.device.SPI,
 ///     clocks.peripheral_clocks.spi.into_enabled_clock(),
+///     clocks.clocks,
 ///     pins.spi_miso,
 ///     pins.spi_mosi,
 ///     pins.spi_sck,
@@ -261,18 +263,31 @@ impl ChipSelectSettings {
 ///     _ => {}
 /// };
 /// ```
-pub struct SpiMaster<FRAMESIZE> {
+pub struct SpiMaster<FRAMESIZE, PINS = DefaultSpiPins> {
     spi: SPI,
-    clock: PhantomData<SpiClock<Enabled>>,
-    miso: PhantomData<Pa12<PfA>>,
-    mosi: PhantomData<Pa13<PfA>>,
-    spck: PhantomData<Pa14<PfA>>,
+    clock: SpiClock<Enabled>,
+    pins: PINS,
     cs: u8,
     lastxfer: bool,
+    master_clock: Hertz,
+    baud: [Option<Hertz>; 4], // Requested baud for each csr, used to recompute scbr
     framesize: PhantomData<FRAMESIZE>,
 }
 
-impl<FRAMESIZE> SpiMaster<FRAMESIZE> {
+/// MISO, MOSI and SPCK pins of a `SpiMaster`
+pub trait SpiPins {}
+
+impl<MISO: MisoPin<SPI>, MOSI: MosiPin<SPI>, SPCK: SpckPin<SPI>> SpiPins for (MISO, MOSI, SPCK) {}
+
+/// The only MISO, MOSI and SPCK mapping available on the SAM4E/4N/4S
+pub type DefaultSpiPins = (Pa12<PfA>, Pa13<PfA>, Pa14<PfA>);
+
+impl<FRAMESIZE, MISO, MOSI, SPCK> SpiMaster<FRAMESIZE, (MISO, MOSI, SPCK)>
+where
+    MISO: MisoPin<SPI>,
+    MOSI: MosiPin<SPI>,
+    SPCK: SpckPin<SPI>,
+{
     /// Initialize SPI as Master
     /// PSM - Peripheral Select Mode
     /// WDRBT - Wait Data Read Before Transfer Enabled
@@ -281,15 +296,16 @@ impl<FRAMESIZE> SpiMaster<FRAMESIZE> {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         spi: SPI,
-        _clock: SpiClock<Enabled>,
-        _miso: Pa12<PfA>,
-        _mosi: Pa13<PfA>,
-        _spck: Pa14<PfA>,
+        clock: SpiClock<Enabled>,
+        clocks: Clocks,
+        miso: MISO,
+        mosi: MOSI,
+        spck: SPCK,
         psm: PeripheralSelectMode,
         wdrbt: bool,
         llb: bool,
         dlybcs: u8,
-    ) -> SpiMaster<FRAMESIZE> {
+    ) -> Self {
         unsafe {
             // Disable SPI
             spi.cr.write_with_zero(|w| w.spidis().set_bit());
@@ -336,16 +352,27 @@ impl<FRAMESIZE> SpiMaster<FRAMESIZE> {
 
         SpiMaster {
             spi,
-            clock: PhantomData,
-            miso: PhantomData,
-            mosi: PhantomData,
-            spck: PhantomData,
+            clock,
+            pins: (miso, mosi, spck),
             cs: 0,           // Default to NPCS0
             lastxfer: false, // Reset to false on each call to send()
+            master_clock: clocks.master_clock(),
+            baud: [None; 4],
             framesize: PhantomData,
         }
     }
 
+    /// Disables the SPI and releases the peripheral, clock and pins
+    pub fn free(self) -> (SPI, SpiClock<Enabled>, MISO, MOSI, SPCK) {
+        unsafe {
+            self.spi.cr.write_with_zero(|w| w.spidis().set_bit());
+        }
+        let (miso, mosi, spck) = self.pins;
+        (self.spi, self.clock, miso, mosi, spck)
+    }
+}
+
+impl<FRAMESIZE, PINS: SpiPins> SpiMaster<FRAMESIZE, PINS> {
     /// Apply settings to a specific channel
     /// Uses cs 0..3 for spi channel settings
     /// When using pcsdec (Chip Decode Select)
@@ -399,13 +426,14 @@ impl<FRAMESIZE> SpiMaster<FRAMESIZE> {
                     .bits_()
                     .bits(settings.bits as u8)
                     .scbr()
-                    .bits(settings.scbr)
+                    .bits(scbr(self.master_clock, settings.baud))
                     .dlybs()
                     .bits(settings.dlybs)
                     .dlybct()
                     .bits(settings.dlybct)
             });
         }
+        self.baud[cs as usize] = Some(settings.baud);
 
         Ok(())
     }
@@ -525,6 +553,19 @@ impl<FRAMESIZE> SpiMaster<FRAMESIZE> {
     }
 }
 
+impl<FRAMESIZE, PINS: SpiPins> ClockChangeListener for SpiMaster<FRAMESIZE, PINS> {
+    /// Recomputes SCBR for every chip select configured using cs_setup()
+    fn clocks_changed(&mut self, clocks: Clocks) {
+        self.master_clock = clocks.master_clock();
+        for (cs, baud) in self.baud.iter().enumerate() {
+            if let Some(baud) = baud {
+                let scbr = scbr(self.master_clock, *baud);
+                self.spi.csr[cs].modify(|_, w| unsafe { w.scbr().bits(scbr) });
+            }
+        }
+    }
+}
+
 /// Used to convert from variable pcs to cs
 /// See (33.8.4)
 /// <https://ww1.microchip.com/downloads/en/DeviceDoc/Atmel-11100-32-bit%20Cortex-M4-Microcontroller-SAM4S_Datasheet.pdf>
@@ -543,7 +584,7 @@ fn variable_pcs_to_cs(pcs: u8) -> Result<u8, Error> {
     }
 }
 
-impl<FRAMESIZE> spi::FullDuplex<FRAMESIZE> for SpiMaster<FRAMESIZE>
+impl<FRAMESIZE, PINS: SpiPins> spi::FullDuplex<FRAMESIZE> for SpiMaster<FRAMESIZE, PINS>
 where
     FRAMESIZE: Copy + From<SpiU16>,
     SpiU16: From<FRAMESIZE> + From<SpiU8>,
@@ -616,7 +657,8 @@ where
     }
 }
 
-impl<FRAMESIZE> crate::hal::blocking::spi::transfer::Default<FRAMESIZE> for SpiMaster<FRAMESIZE>
+impl<FRAMESIZE, PINS: SpiPins> crate::hal::blocking::spi::transfer::Default<FRAMESIZE>
+    for SpiMaster<FRAMESIZE, PINS>
 where
     FRAMESIZE: Copy + From<SpiU16>,
     SpiU16: From<FRAMESIZE> + From<SpiU8>,
@@ -624,7 +666,7 @@ where
 {
 }
 
-impl crate::hal::blocking::spi::Write<SpiU8> for SpiMaster<SpiU8> {
+impl<PINS: SpiPins> crate::hal::blocking::spi::Write<SpiU8> for SpiMaster<SpiU8, PINS> {
     type Error = Error;
 
     fn write(&mut self, words: &[SpiU8]) -> Result<(), Error> {
@@ -632,7 +674,7 @@ impl crate::hal::blocking::spi::Write<SpiU8> for SpiMaster<SpiU8> {
     }
 }
 
-impl crate::hal::blocking::spi::Write<SpiU16> for SpiMaster<SpiU16> {
+impl<PINS: SpiPins> crate::hal::blocking::spi::Write<SpiU16> for SpiMaster<SpiU16, PINS> {
     type Error = Error;
 
     fn write(&mut self, words: &[SpiU16]) -> Result<(), Error> {
@@ -647,7 +689,7 @@ pub trait SpiReadWrite<T> {
     fn spi_write(&mut self, words: &[T]) -> Result<(), Error>;
 }
 
-impl<FRAMESIZE> SpiReadWrite<FRAMESIZE> for SpiMaster<FRAMESIZE>
+impl<FRAMESIZE, PINS: SpiPins> SpiReadWrite<FRAMESIZE> for SpiMaster<FRAMESIZE, PINS>
 where
     FRAMESIZE: Copy + From<SpiU16>,
     SpiU16: From<FRAMESIZE> + From<SpiU8>,
@@ -730,23 +772,26 @@ pub struct Fixed;
 /// <https://ww1.microchip.com/downloads/en/DeviceDoc/Atmel-11100-32-bit%20Cortex-M4-Microcontroller-SAM4S_Datasheet.pdf>
 pub struct Variable;
 
-pub struct SpiPayload<MODE, FRAMESIZE> {
-    spi: SpiMaster<FRAMESIZE>,
+pub struct SpiPayload<MODE, FRAMESIZE, PINS = DefaultSpiPins> {
+    spi: SpiMaster<FRAMESIZE, PINS>,
     _mode: PhantomData<MODE>,
 }
 
-pub type SpiRxDma<MODE, FRAMESIZE> = RxDma<SpiPayload<MODE, FRAMESIZE>>;
-pub type SpiTxDma<MODE, FRAMESIZE> = TxDma<SpiPayload<MODE, FRAMESIZE>>;
-pub type SpiRxTxDma<MODE, FRAMESIZE> = RxTxDma<SpiPayload<MODE, FRAMESIZE>>;
+pub type SpiRxDma<MODE, FRAMESIZE, PINS = DefaultSpiPins> =
+    RxDma<SpiPayload<MODE, FRAMESIZE, PINS>>;
+pub type SpiTxDma<MODE, FRAMESIZE, PINS = DefaultSpiPins> =
+    TxDma<SpiPayload<MODE, FRAMESIZE, PINS>>;
+pub type SpiRxTxDma<MODE, FRAMESIZE, PINS = DefaultSpiPins> =
+    RxTxDma<SpiPayload<MODE, FRAMESIZE, PINS>>;
 
 macro_rules! spi_pdc {
     (
         $Mode:ident, $Framesize:ident
     ) => {
         paste! {
-            impl SpiMaster<$Framesize> {
+            impl<PINS: SpiPins> SpiMaster<$Framesize, PINS> {
                 /// SPI with PDC, Rx only
-                pub fn with_pdc_rx(self) -> SpiRxDma<$Mode, $Framesize> {
+                pub fn with_pdc_rx(self) -> SpiRxDma<$Mode, $Framesize, PINS> {
                     let payload = SpiPayload {
                         spi: self,
                         _mode: PhantomData,
@@ -755,7 +800,7 @@ macro_rules! spi_pdc {
                 }
 
                 /// SPI with PDC, Tx only
-                pub fn with_pdc_tx(self) -> SpiTxDma<$Mode, $Framesize> {
+                pub fn with_pdc_tx(self) -> SpiTxDma<$Mode, $Framesize, PINS> {
                     let payload = SpiPayload {
                         spi: self,
                         _mode: PhantomData,
@@ -798,6 +843,7 @@ macro_rules! spi_pdc {
                 /// let mut spi = SpiMaster::<SpiU8>::new(
                 ///     cx.device.SPI,
                 ///     clocks.peripheral_clocks.spi.into_enabled_clock(),
+                ///     clocks.clocks,
                 ///     pins.spi_miso,
                 ///     pins.spi_mosi,
                 ///     pins.spi_sck,
@@ -815,7 +861,7 @@ macro_rules! spi_pdc {
                 /// let txfr = spi.read_write_len(spi_rx_buf, spi_tx_buf, 7);
                 /// let ((rx_buf, tx_buf), spi) = txfr.wait();
                 /// ```
-                pub fn with_pdc_rxtx(self) -> SpiRxTxDma<$Mode, $Framesize> {
+                pub fn with_pdc_rxtx(self) -> SpiRxTxDma<$Mode, $Framesize, PINS> {
                     let payload = SpiPayload {
                         spi: self,
                         _mode: PhantomData,
@@ -825,36 +871,36 @@ macro_rules! spi_pdc {
             }
 
             // Setup PDC Rx/Tx functionality
-            pub type [<SpiMaster $Framesize>] = SpiMaster<$Framesize>;
-            pdc_rx! { [<SpiMaster $Framesize>]: spi, sr }
-            pdc_tx! { [<SpiMaster $Framesize>]: spi, sr }
-            pdc_rxtx! { [<SpiMaster $Framesize>]: spi }
+            pub type [<SpiMaster $Framesize>]<PINS = DefaultSpiPins> = SpiMaster<$Framesize, PINS>;
+            pdc_rx! { [<SpiMaster $Framesize>]<PINS: SpiPins>: spi, sr, ier, idr }
+            pdc_tx! { [<SpiMaster $Framesize>]<PINS: SpiPins>: spi, sr, ier, idr }
+            pdc_rxtx! { [<SpiMaster $Framesize>]<PINS: SpiPins>: spi }
 
-            impl Transmit for SpiTxDma<$Mode, $Framesize> {
+            impl<PINS: SpiPins> Transmit for SpiTxDma<$Mode, $Framesize, PINS> {
                 type ReceivedWord = $Framesize;
             }
 
-            impl Receive for SpiRxDma<$Mode, $Framesize> {
+            impl<PINS: SpiPins> Receive for SpiRxDma<$Mode, $Framesize, PINS> {
                 type TransmittedWord = $Framesize;
             }
 
-            impl Receive for SpiRxTxDma<$Mode, $Framesize> {
+            impl<PINS: SpiPins> Receive for SpiRxTxDma<$Mode, $Framesize, PINS> {
                 type TransmittedWord = $Framesize;
             }
 
-            impl Transmit for SpiRxTxDma<$Mode, $Framesize> {
+            impl<PINS: SpiPins> Transmit for SpiRxTxDma<$Mode, $Framesize, PINS> {
                 type ReceivedWord = $Framesize;
             }
 
-            impl SpiRxDma<$Mode, $Framesize> {
+            impl<PINS: SpiPins> SpiRxDma<$Mode, $Framesize, PINS> {
                 /// Reverts SpiRxDma back to SpiMaster
-                pub fn revert(mut self) -> SpiMaster<$Framesize> {
+                pub fn revert(mut self) -> SpiMaster<$Framesize, PINS> {
                     self.payload.spi.stop_rx_pdc();
                     self.payload.spi
                 }
             }
 
-            impl<B> ReadDma<B, $Framesize> for SpiRxDma<$Mode, $Framesize>
+            impl<B, PINS: SpiPins> ReadDma<B, $Framesize> for SpiRxDma<$Mode, $Framesize, PINS>
             where
                 Self: TransferPayload,
                 B: WriteBuffer<Word = $Framesize>,
@@ -874,7 +920,7 @@ macro_rules! spi_pdc {
                 }
             }
 
-            impl TransferPayload for SpiRxDma<$Mode, $Framesize> {
+            impl<PINS: SpiPins> TransferPayload for SpiRxDma<$Mode, $Framesize, PINS> {
                 fn start(&mut self) {
                     self.payload.spi.start_rx_pdc();
                 }
@@ -886,15 +932,15 @@ macro_rules! spi_pdc {
                 }
             }
 
-            impl SpiTxDma<$Mode, $Framesize> {
+            impl<PINS: SpiPins> SpiTxDma<$Mode, $Framesize, PINS> {
                 /// Reverts SpiTxDma back to SpiMaster
-                pub fn revert(mut self) -> SpiMaster<$Framesize> {
+                pub fn revert(mut self) -> SpiMaster<$Framesize, PINS> {
                     self.payload.spi.stop_tx_pdc();
                     self.payload.spi
                 }
             }
 
-            impl<B> WriteDma<B, $Framesize> for SpiTxDma<$Mode, $Framesize>
+            impl<B, PINS: SpiPins> WriteDma<B, $Framesize> for SpiTxDma<$Mode, $Framesize, PINS>
             where
                 Self: TransferPayload,
                 B: ReadBuffer<Word = $Framesize>,
@@ -914,7 +960,7 @@ macro_rules! spi_pdc {
                 }
             }
 
-            impl TransferPayload for SpiTxDma<$Mode, $Framesize> {
+            impl<PINS: SpiPins> TransferPayload for SpiTxDma<$Mode, $Framesize, PINS> {
                 fn start(&mut self) {
                     self.payload.spi.start_tx_pdc();
                 }
@@ -926,15 +972,15 @@ macro_rules! spi_pdc {
                 }
             }
 
-            impl SpiRxTxDma<$Mode, $Framesize> {
+            impl<PINS: SpiPins> SpiRxTxDma<$Mode, $Framesize, PINS> {
                 /// Reverts SpiRxTxDma back to SpiMaster
-                pub fn revert(mut self) -> SpiMaster<$Framesize> {
+                pub fn revert(mut self) -> SpiMaster<$Framesize, PINS> {
                     self.payload.spi.stop_rxtx_pdc();
                     self.payload.spi
                 }
             }
 
-            impl<RXB, TXB> ReadWriteDma<RXB, TXB, $Framesize> for SpiRxTxDma<$Mode, $Framesize>
+            impl<RXB, TXB, PINS: SpiPins> ReadWriteDma<RXB, TXB, $Framesize> for SpiRxTxDma<$Mode, $Framesize, PINS>
             where
                 Self: TransferPayload,
                 RXB: WriteBuffer<Word = $Framesize>,
@@ -962,7 +1008,7 @@ macro_rules! spi_pdc {
                 }
             }
 
-            impl<RXB, TXB> ReadWriteDmaLen<RXB, TXB, $Framesize> for SpiRxTxDma<$Mode, $Framesize>
+            impl<RXB, TXB, PINS: SpiPins> ReadWriteDmaLen<RXB, TXB, $Framesize> for SpiRxTxDma<$Mode, $Framesize, PINS>
             where
                 Self: TransferPayload,
                 RXB: WriteBuffer<Word = $Framesize>,
@@ -993,7 +1039,7 @@ macro_rules! spi_pdc {
                 }
             }
 
-            impl TransferPayload for SpiRxTxDma<$Mode, $Framesize> {
+            impl<PINS: SpiPins> TransferPayload for SpiRxTxDma<$Mode, $Framesize, PINS> {
                 fn start(&mut self) {
                     self.payload.spi.start_rxtx_pdc();
                 }
@@ -172,6 +172,17 @@ pub struct Smc {
     pub chip_select1: ChipSelect1<Uninitialized>,
     pub chip_select2: ChipSelect2<Uninitialized>,
     pub chip_select3: ChipSelect3<Uninitialized>,
+    clock: SmcClock<Enabled>,
+    pins: SmcPins,
+}
+
+struct SmcPins {
+    ncs1: NCS1,
+    ncs3: NCS3,
+    nrd: Pc11<PfA>,
+    nwe: Pc8<PfA>,
+    data_lines: DataLines,
+    address_lines: AddressLines,
 }
 
 pub enum NCS1 {
@@ -188,7 +199,7 @@ pub enum NCS3 {
     D19(Pd19<PfA>),
 }
 
-type DataLines = (
+pub type DataLines = (
     Pc0<PfA>,
     Pc1<PfA>,
     Pc2<PfA>,
@@ -199,7 +210,7 @@ type DataLines = (
     Pc7<PfA>,
 );
 
-type AddressLines = (
+pub type AddressLines = (
     Pc18<PfA>,
     Pc19<PfA>,
     Pc20<PfA>,
@@ -228,25 +239,59 @@ type AddressLines = (
 
 impl Smc {
     pub fn new(
-        _clock: SmcClock<Enabled>,
+        clock: SmcClock<Enabled>,
 
-        _ncs1: NCS1,
-        _ncs3: NCS3,
+        ncs1: NCS1,
+        ncs3: NCS3,
 
-        _nrd: Pc11<PfA>,
-        _nwe: Pc8<PfA>,
+        nrd: Pc11<PfA>,
+        nwe: Pc8<PfA>,
 
-        _data_lines: DataLines,
-        _address_lines: AddressLines,
+        data_lines: DataLines,
+        address_lines: AddressLines,
     ) -> Self {
         Smc {
             chip_select0: ChipSelect0::<Uninitialized> { _mode: PhantomData },
             chip_select1: ChipSelect1::<Uninitialized> { _mode: PhantomData },
             chip_select2: ChipSelect2::<Uninitialized> { _mode: PhantomData },
             chip_select3: ChipSelect3::<Uninitialized> { _mode: PhantomData },
+            clock,
+            pins: SmcPins {
+                ncs1,
+                ncs3,
+                nrd,
+                nwe,
+                data_lines,
+                address_lines,
+            },
         }
     }
 
+    /// Releases the clock and pins
+    #[allow(clippy::type_complexity)]
+    pub fn free(
+        self,
+    ) -> (
+        SmcClock<Enabled>,
+        NCS1,
+        NCS3,
+        Pc11<PfA>,
+        Pc8<PfA>,
+        DataLines,
+        AddressLines,
+    ) {
+        let pins = self.pins;
+        (
+            self.clock,
+            pins.ncs1,
+            pins.ncs3,
+            pins.nrd,
+            pins.nwe,
+            pins.data_lines,
+            pins.address_lines,
+        )
+    }
+
     pub fn base_address(&self, chip_select: u8) -> usize {
         match chip_select {
             0 => 0x6000_0000,
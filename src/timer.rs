@@ -14,7 +14,7 @@ use crate::pac::TC1;
 #[cfg(feature = "atsam4e_e")]
 use crate::pac::TC2;
 
-use crate::clock::{Enabled, Tc0Clock, Tc1Clock, Tc2Clock};
+use crate::clock::{ClockChangeListener, Clocks, Enabled, Tc0Clock, Tc1Clock, Tc2Clock};
 #[cfg(any(feature = "atsam4e_e", feature = "atsam4n_c", feature = "atsam4s_c"))]
 use crate::clock::{Tc3Clock, Tc4Clock, Tc5Clock};
 #[cfg(feature = "atsam4e_e")]
@@ -54,7 +54,7 @@ impl ClockSource {
 /// Before a hardware timer can be used, it must first
 /// have a clock configured.
 pub struct TimerCounter<TC> {
-    _tc: TC,
+    tc: TC,
 }
 
 pub struct TimerCounterChannels<
@@ -69,12 +69,13 @@ pub struct TimerCounterChannels<
     pub ch0: TimerCounterChannel<TC, CLK1, 0, FREQ1>,
     pub ch1: TimerCounterChannel<TC, CLK2, 1, FREQ2>,
     pub ch2: TimerCounterChannel<TC, CLK3, 2, FREQ3>,
+    tc: TC,
 }
 
 pub struct TimerCounterChannel<TC, CLK, const CH: u8, const FREQ: u32> {
     freq: Hertz,
     source: ClockSource,
-    _clock: PhantomData<CLK>,
+    clock: CLK,
     _mode: PhantomData<TC>,
 }
 
@@ -106,6 +107,7 @@ impl TimerCounter<$TC>
     ///     clocks.peripheral_clocks.tc_0.into_enabled_clock(),
     ///     clocks.peripheral_clocks.tc_1.into_enabled_clock(),
     ///     clocks.peripheral_clocks.tc_2.into_enabled_clock(),
+    ///     clocks.clocks,
     /// );
     ///
     /// let mut tcc0 = tc0_chs.ch0;
@@ -130,23 +132,36 @@ impl TimerCounter<$TC>
         }
 
         Self {
-            _tc: tc,
+            tc,
         }
     }
 
+    /// Releases the TC peripheral
+    pub fn free(self) -> $TC {
+        self.tc
+    }
+
     /// Splits the TimerCounter module into 3 channels
     /// Defaults to MckDiv2 clock source
-    pub fn split<const FREQ1: u32, const FREQ2: u32, const FREQ3: u32>(self, clock1: $clock1<Enabled>, _clock2: $clock2<Enabled>, _clock3: $clock3<Enabled>) -> TimerCounterChannels<$TC, $clock1<Enabled>, $clock2<Enabled>, $clock3<Enabled>, FREQ1, FREQ2, FREQ3> {
-        let freq = clock1.frequency();
+    pub fn split<const FREQ1: u32, const FREQ2: u32, const FREQ3: u32>(self, clock1: $clock1<Enabled>, clock2: $clock2<Enabled>, clock3: $clock3<Enabled>, clocks: Clocks) -> TimerCounterChannels<$TC, $clock1<Enabled>, $clock2<Enabled>, $clock3<Enabled>, FREQ1, FREQ2, FREQ3> {
+        let freq = clocks.master_clock();
         let source = ClockSource::MckDiv2;
         TimerCounterChannels::<$TC, $clock1<Enabled>, $clock2<Enabled>, $clock3<Enabled>, FREQ1, FREQ2, FREQ3> {
-            ch0: TimerCounterChannel { _clock: PhantomData, freq, source, _mode: PhantomData },
-            ch1: TimerCounterChannel { _clock: PhantomData, freq, source, _mode: PhantomData },
-            ch2: TimerCounterChannel { _clock: PhantomData, freq, source, _mode: PhantomData },
+            ch0: TimerCounterChannel { clock: clock1, freq, source, _mode: PhantomData },
+            ch1: TimerCounterChannel { clock: clock2, freq, source, _mode: PhantomData },
+            ch2: TimerCounterChannel { clock: clock3, freq, source, _mode: PhantomData },
+            tc: self.tc,
         }
     }
 }
 
+impl<const FREQ1: u32, const FREQ2: u32, const FREQ3: u32> TimerCounterChannels<$TC, $clock1<Enabled>, $clock2<Enabled>, $clock3<Enabled>, FREQ1, FREQ2, FREQ3> {
+    /// Stops all 3 channels and releases the TimerCounter and channel clocks
+    pub fn free(self) -> (TimerCounter<$TC>, $clock1<Enabled>, $clock2<Enabled>, $clock3<Enabled>) {
+        (TimerCounter { tc: self.tc }, self.ch0.free(), self.ch1.free(), self.ch2.free())
+    }
+}
+
 impl<CLK, const CH: u8, const FREQ: u32> TimerCounterChannel<$TC, CLK, CH, FREQ> {
     /// Set the input clock
     pub fn clock_input(&mut self, source: ClockSource) {
@@ -183,6 +198,18 @@ impl<CLK, const CH: u8, const FREQ: u32> TimerCounterChannel<$TC, CLK, CH, FREQ>
         }
     }
 
+    /// Stops the channel, disables its interrupt and releases the channel clock
+    pub fn free(mut self) -> CLK {
+        self.disable_interrupt();
+        match CH {
+            0 => $TC::borrow_unchecked(|tc| unsafe { tc.ccr0.write_with_zero(|w| w.clkdis().set_bit())}),
+            1 => $TC::borrow_unchecked(|tc| unsafe { tc.ccr1.write_with_zero(|w| w.clkdis().set_bit())}),
+            2 => $TC::borrow_unchecked(|tc| unsafe { tc.ccr2.write_with_zero(|w| w.clkdis().set_bit())}),
+            _ => panic!("Invalid TimerCounterChannel: {}", CH),
+        }
+        self.clock
+    }
+
     /// Clear interrupt status
     pub fn clear_interrupt_flags(&mut self) -> bool {
         match CH {
@@ -193,6 +220,31 @@ impl<CLK, const CH: u8, const FREQ: u32> TimerCounterChannel<$TC, CLK, CH, FREQ>
         }
     }
 }
+impl<CLK, const CH: u8, const FREQ: u32> ClockChangeListener for TimerCounterChannel<$TC, CLK, CH, FREQ> {
+    /// Selects the MCK divider that still generates FREQ
+    ///
+    /// # Panics
+    /// If no MCK divider generates FREQ at the new master clock frequency
+    fn clocks_changed(&mut self, clocks: Clocks) {
+        let master_clock = clocks.master_clock();
+        self.freq = master_clock;
+        if self.source == ClockSource::Slck32768Hz {
+            return;
+        }
+
+        for source in [ClockSource::MckDiv2, ClockSource::MckDiv8, ClockSource::MckDiv32, ClockSource::MckDiv128] {
+            if (master_clock / source.div()).raw() == FREQ {
+                self.clock_input(source);
+                return;
+            }
+        }
+        panic!(
+            "No MCK divider generates the {} Hz timer frequency from a {} Hz master clock",
+            FREQ,
+            master_clock.raw()
+        );
+    }
+}
 impl<CLK, const CH: u8, const FREQ: u32> Periodic for TimerCounterChannel<$TC, CLK, CH, FREQ> {}
 impl<CLK, const CH: u8, const FREQ: u32> CountDown for TimerCounterChannel<$TC, CLK, CH, FREQ> {
     type Time = TimerDuration<FREQ>;
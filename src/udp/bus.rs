@@ -1,11 +1,11 @@
-use crate::clock::{Disabled, UdpClock};
+use crate::clock::{Clocks, Disabled, UdpClock};
 use crate::gpio::{Pb10, Pb11, SysFn};
 use crate::pac::{PMC, UDP};
 use crate::udp::{frm_num, Endpoint};
 use crate::BorrowUnchecked;
 use core::cell::RefCell;
-use core::marker::PhantomData;
 use cortex_m::interrupt::Mutex;
+use fugit::HertzU32 as Hertz;
 use usb_device::{
     bus::{PollResult, UsbBus},
     endpoint::{EndpointAddress, EndpointType},
@@ -17,18 +17,30 @@ use crate::clock::{disable_pllb_clock, reenable_pllb_clock, wait_for_pllb_lock};
 
 pub const NUM_ENDPOINTS: usize = 8;
 
+/// USB device bus (UDP)
+///
+/// NOTE: The master clock frequency is captured by `new()` and used for the disconnect delay of
+/// `force_reset()`. The bus is owned by the `UsbBusAllocator` and cannot be notified of clock
+/// changes, so reconfiguring the master clock only affects the length of that delay.
 pub struct UdpBus {
     udp: Mutex<RefCell<UDP>>,
     endpoints: [Mutex<RefCell<Endpoint>>; NUM_ENDPOINTS],
-    clock: PhantomData<UdpClock<Disabled>>,
-    ddm: PhantomData<Pb10<SysFn>>,
-    ddp: PhantomData<Pb11<SysFn>>,
+    clock: UdpClock<Disabled>,
+    ddm: Pb10<SysFn>,
+    ddp: Pb11<SysFn>,
     sof_errors: Mutex<RefCell<u32>>,
+    master_clock: Hertz,
 }
 
 impl UdpBus {
     /// Initialize UDP as a USB device
-    pub fn new(udp: UDP, _clock: UdpClock<Disabled>, _ddm: Pb10<SysFn>, _ddp: Pb11<SysFn>) -> Self {
+    pub fn new(
+        udp: UDP,
+        clock: UdpClock<Disabled>,
+        clocks: Clocks,
+        ddm: Pb10<SysFn>,
+        ddp: Pb11<SysFn>,
+    ) -> Self {
         let endpoints = [
             Mutex::new(RefCell::new(Endpoint::new(0))),
             Mutex::new(RefCell::new(Endpoint::new(1))),
@@ -44,13 +56,26 @@ impl UdpBus {
         Self {
             udp,
             endpoints,
-            clock: PhantomData,
-            ddm: PhantomData,
-            ddp: PhantomData,
+            clock,
+            ddm,
+            ddp,
             sof_errors,
+            master_clock: clocks.master_clock(),
         }
     }
 
+    /// Disables the transceiver and UDP clocks and releases the peripheral, clock and pins
+    pub fn free(self) -> (UDP, UdpClock<Disabled>, Pb10<SysFn>, Pb11<SysFn>) {
+        self.disable();
+        self.disable_periph_clk();
+        self.disable_pll_clk();
+        // UsbBus must be Sync so UDP has to stay in the Mutex, which has no into_inner()
+        // SAFETY: UDP is a zero-sized token and the instance owned by self is dropped here, so
+        // only one UDP instance exists after this point. No other peripheral is stolen.
+        let udp: UDP = unsafe { core::mem::transmute(()) };
+        (udp, self.clock, self.ddm, self.ddp)
+    }
+
     /// Enabled clocks for UDP
     /// Useful for resume interrupts as well as remote wakeup
     fn enable_pll_clk(&self) {
@@ -619,8 +644,7 @@ impl UsbBus for UdpBus {
         self.disable();
 
         // Need to wait for the USB device to disconnect
-        let freq = crate::clock::get_master_clock_frequency();
-        cortex_m::asm::delay((freq / 1000).raw()); // 1 ms
+        cortex_m::asm::delay((self.master_clock / 1000).raw()); // 1 ms
 
         self._enable();
         Ok(())